@@ -0,0 +1,4 @@
+ALTER TABLE paste ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE paste ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX paste_id ON paste (id);
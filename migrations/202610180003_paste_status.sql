@@ -0,0 +1,4 @@
+ALTER TABLE paste ADD COLUMN status TEXT NOT NULL DEFAULT 'active'
+  CHECK (status IN ('active', 'quarantined', 'taken_down'));
+ALTER TABLE paste ADD COLUMN status_reason TEXT;
+ALTER TABLE paste ADD COLUMN status_report_id INTEGER REFERENCES report (id) ON DELETE SET NULL;
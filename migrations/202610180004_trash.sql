@@ -0,0 +1,2 @@
+ALTER TABLE paste ADD COLUMN deleted_at INTEGER;
+ALTER TABLE report ADD COLUMN deleted_at INTEGER;
@@ -0,0 +1,33 @@
+CREATE TABLE legal_hold (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  target_type TEXT NOT NULL CHECK (target_type IN ('paste', 'report')),
+  target_id TEXT NOT NULL,
+  case_reference TEXT NOT NULL,
+  created_at INTEGER NOT NULL,
+  ends_at INTEGER,
+  lifted_at INTEGER
+);
+
+CREATE INDEX legal_hold_target ON legal_hold (target_type, target_id);
+
+CREATE VIEW active_legal_hold AS
+  SELECT * FROM legal_hold
+  WHERE lifted_at IS NULL
+    AND (ends_at IS NULL OR ends_at > CAST(strftime('%s', 'now') AS INTEGER) * 1000);
+
+-- Last line of defence: whatever code path tries it, held rows cannot be deleted.
+CREATE TRIGGER paste_legal_hold BEFORE DELETE ON paste
+WHEN EXISTS (
+  SELECT 1 FROM active_legal_hold WHERE target_type = 'paste' AND target_id = OLD.id
+)
+BEGIN
+  SELECT RAISE(ABORT, 'paste is under legal hold');
+END;
+
+CREATE TRIGGER report_legal_hold BEFORE DELETE ON report
+WHEN EXISTS (
+  SELECT 1 FROM active_legal_hold WHERE target_type = 'report' AND target_id = CAST(OLD.id AS TEXT)
+)
+BEGIN
+  SELECT RAISE(ABORT, 'report is under legal hold');
+END;
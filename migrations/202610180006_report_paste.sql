@@ -0,0 +1,11 @@
+CREATE TABLE report_paste (
+  report_id INTEGER NOT NULL REFERENCES report (id) ON DELETE CASCADE,
+  paste_id TEXT NOT NULL,
+  PRIMARY KEY (report_id, paste_id)
+);
+
+CREATE INDEX report_paste_paste_id ON report_paste (paste_id);
+
+INSERT OR IGNORE INTO report_paste ( report_id, paste_id )
+  SELECT report.id, paste.id FROM report
+  JOIN paste ON report.links LIKE '%/' || paste.id || '#%';
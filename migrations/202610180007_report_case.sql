@@ -0,0 +1,16 @@
+ALTER TABLE report ADD COLUMN status TEXT NOT NULL DEFAULT 'open'
+  CHECK (status IN ('open', 'in_review', 'actioned', 'rejected', 'closed'));
+ALTER TABLE report ADD COLUMN assignee TEXT;
+ALTER TABLE report ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE report ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE report ADD COLUMN resolved_at INTEGER;
+
+CREATE TABLE report_note (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  report_id INTEGER NOT NULL REFERENCES report (id) ON DELETE CASCADE,
+  author TEXT NOT NULL,
+  body TEXT NOT NULL,
+  created_at INTEGER NOT NULL
+);
+
+CREATE INDEX report_note_report_id ON report_note (report_id);
@@ -0,0 +1,17 @@
+CREATE TABLE counter_notice (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  paste_id TEXT NOT NULL,
+  report_id INTEGER NOT NULL REFERENCES report (id) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  email TEXT NOT NULL,
+  address TEXT NOT NULL,
+  statement TEXT NOT NULL,
+  signature TEXT NOT NULL,
+  created_at INTEGER NOT NULL,
+  restore_after INTEGER NOT NULL,
+  legal_action_pending BOOLEAN NOT NULL DEFAULT FALSE,
+  restored_at INTEGER
+);
+
+CREATE INDEX counter_notice_paste_id ON counter_notice (paste_id);
+CREATE INDEX counter_notice_report_id ON counter_notice (report_id);
@@ -0,0 +1,6 @@
+ALTER TABLE report ADD COLUMN verified_at INTEGER;
+ALTER TABLE report ADD COLUMN verification_token TEXT;
+
+UPDATE report SET verified_at = created_at;
+
+CREATE UNIQUE INDEX report_verification_token ON report (verification_token);
@@ -0,0 +1,7 @@
+ALTER TABLE report ADD COLUMN ticket_id TEXT;
+
+UPDATE report SET ticket_id = hex(randomblob(10));
+UPDATE report SET ticket_id = 'AP-' || substr(ticket_id, 1, 4) || '-' || substr(ticket_id, 5, 4)
+  || '-' || substr(ticket_id, 9, 4) || '-' || substr(ticket_id, 13, 4) || '-' || substr(ticket_id, 17, 4);
+
+CREATE UNIQUE INDEX report_ticket_id ON report (ticket_id);
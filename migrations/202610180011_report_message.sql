@@ -0,0 +1,11 @@
+CREATE TABLE report_message (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  report_id INTEGER NOT NULL REFERENCES report (id) ON DELETE CASCADE,
+  direction TEXT NOT NULL CHECK (direction IN ('outbound', 'inbound')),
+  author TEXT NOT NULL,
+  subject TEXT NOT NULL,
+  body TEXT NOT NULL,
+  created_at INTEGER NOT NULL
+);
+
+CREATE INDEX report_message_report_id ON report_message (report_id);
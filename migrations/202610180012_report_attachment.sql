@@ -0,0 +1,11 @@
+CREATE TABLE report_attachment (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  report_id INTEGER NOT NULL REFERENCES report (id) ON DELETE CASCADE,
+  filename TEXT NOT NULL,
+  content_type TEXT NOT NULL,
+  size INTEGER NOT NULL,
+  content BLOB NOT NULL,
+  created_at INTEGER NOT NULL
+);
+
+CREATE INDEX report_attachment_report_id ON report_attachment (report_id);
@@ -0,0 +1,5 @@
+ALTER TABLE report ADD COLUMN category TEXT NOT NULL DEFAULT 'dmca'
+  CHECK (category IN ('dmca', 'csam', 'malware', 'phishing', 'harassment'));
+ALTER TABLE report ADD COLUMN priority INTEGER NOT NULL DEFAULT 3;
+
+CREATE INDEX report_priority ON report (priority, id);
@@ -0,0 +1,12 @@
+CREATE TABLE trusted_flagger (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  name TEXT NOT NULL,
+  email TEXT NOT NULL,
+  key_hash TEXT NOT NULL UNIQUE,
+  immediate_quarantine BOOLEAN NOT NULL DEFAULT FALSE,
+  created_at INTEGER NOT NULL,
+  last_used_at INTEGER,
+  revoked_at INTEGER
+);
+
+ALTER TABLE report ADD COLUMN trusted_flagger_id INTEGER REFERENCES trusted_flagger (id);
@@ -0,0 +1,3 @@
+ALTER TABLE report ADD COLUMN anonymised_at INTEGER;
+
+CREATE INDEX report_resolved_at ON report (status, resolved_at);
@@ -0,0 +1,8 @@
+-- Reporter PII is encrypted by the application. A NULL key id marks rows
+-- written before encryption, which stay readable until re-encrypted.
+ALTER TABLE report ADD COLUMN pii_key_id TEXT;
+ALTER TABLE report ADD COLUMN email_index TEXT;
+ALTER TABLE report_message ADD COLUMN key_id TEXT;
+ALTER TABLE report_attachment ADD COLUMN key_id TEXT;
+
+CREATE INDEX report_email_index ON report (email_index);
@@ -0,0 +1 @@
+ALTER TABLE report ADD COLUMN locale TEXT NOT NULL DEFAULT 'en';
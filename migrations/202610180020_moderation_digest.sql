@@ -0,0 +1,4 @@
+CREATE TABLE moderation_digest (
+  date TEXT PRIMARY KEY NOT NULL,
+  sent_at INTEGER NOT NULL
+);
@@ -0,0 +1,8 @@
+-- Held pastes cannot be rewritten either, whatever code path tries it.
+CREATE TRIGGER paste_legal_hold_update BEFORE UPDATE OF content ON paste
+WHEN NEW.content IS NOT OLD.content AND EXISTS (
+  SELECT 1 FROM active_legal_hold WHERE target_type = 'paste' AND target_id = OLD.id
+)
+BEGIN
+  SELECT RAISE(ABORT, 'paste is under legal hold');
+END;
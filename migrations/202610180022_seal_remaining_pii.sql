@@ -0,0 +1,5 @@
+-- Counter-notices, flagger contacts and operator notes are encrypted like
+-- reporter data. A NULL key id marks rows written before that.
+ALTER TABLE counter_notice ADD COLUMN key_id TEXT;
+ALTER TABLE trusted_flagger ADD COLUMN key_id TEXT;
+ALTER TABLE report_note ADD COLUMN key_id TEXT;
@@ -0,0 +1,8 @@
+ALTER TABLE report ADD COLUMN closed_at INTEGER;
+
+-- Closing is the last thing that happens to most cases, so the last update
+-- is the best estimate of when existing ones were closed.
+UPDATE report SET closed_at = updated_at WHERE status = 'closed';
+
+DROP INDEX report_resolved_at;
+CREATE INDEX report_closed_at ON report (status, closed_at);
@@ -0,0 +1,188 @@
+use anyhow::{anyhow, bail, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt;
+
+use crate::error::Error;
+
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 24;
+
+#[derive(Clone)]
+struct Key {
+    id: String,
+    cipher: XChaCha20Poly1305,
+    index_key: [u8; KEY_LENGTH],
+}
+
+/// Server-side keys protecting reporters' personal data at rest. The first
+/// key encrypts everything written; the others are kept only to read rows
+/// written before a rotation, until they have been re-encrypted.
+#[derive(Clone)]
+pub struct Keyring {
+    keys: Vec<Key>,
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids: Vec<&str> = self.keys.iter().map(|key| key.id.as_str()).collect();
+        f.debug_struct("Keyring").field("keys", &ids).finish()
+    }
+}
+
+impl Keyring {
+    pub fn new(keys: Vec<(String, [u8; KEY_LENGTH])>) -> anyhow::Result<Keyring> {
+        if keys.is_empty() {
+            bail!("at least one key is required");
+        }
+        let mut ring: Vec<Key> = Vec::new();
+        for (id, secret) in keys {
+            if id.is_empty() || id.contains([':', ',']) {
+                bail!("invalid key id {:?}", id);
+            }
+            if ring.iter().any(|key| key.id == id) {
+                bail!("duplicate key id {:?}", id);
+            }
+            let mut index_key = [0; KEY_LENGTH];
+            Hkdf::<Sha256>::new(None, &secret)
+                .expand(b"anonpaste blind index", &mut index_key)
+                .map_err(|_| anyhow!("cannot derive index key"))?;
+            ring.push(Key {
+                id,
+                cipher: XChaCha20Poly1305::new(&secret.into()),
+                index_key,
+            });
+        }
+        Ok(Keyring { keys: ring })
+    }
+
+    /// Parses `id:key,id:key,...`, where each key is 32 base64 encoded bytes
+    /// and the first one is the active key.
+    pub fn parse(spec: &str) -> anyhow::Result<Keyring> {
+        let mut keys = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (id, secret) = entry
+                .split_once(':')
+                .context("keys must look like id:base64")?;
+            let secret: [u8; KEY_LENGTH] = STANDARD
+                .decode(secret.trim())
+                .ok()
+                .and_then(|secret| secret.try_into().ok())
+                .with_context(|| format!("key {} must be 32 base64 encoded bytes", id))?;
+            keys.push((id.trim().to_string(), secret));
+        }
+        Keyring::new(keys)
+    }
+
+    /// Id of the key new data is encrypted with.
+    pub fn active_key_id(&self) -> &str {
+        &self.keys[0].id
+    }
+
+    fn key(&self, id: &str) -> Result<&Key, Error> {
+        self.keys
+            .iter()
+            .find(|key| key.id == id)
+            .ok_or_else(|| anyhow!("no key {:?} in the keyring", id).into())
+    }
+
+    /// Encrypts with the active key. `context` names the column the value is
+    /// stored in, so a ciphertext copied to another column fails to decrypt.
+    pub fn seal(&self, context: &str, plaintext: &[u8]) -> Vec<u8> {
+        let nonce: [u8; NONCE_LENGTH] = rand::random();
+        let ciphertext = self.keys[0]
+            .cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: context.as_bytes(),
+                },
+            )
+            .expect("encryption cannot fail for in-memory buffers");
+        [&nonce[..], &ciphertext].concat()
+    }
+
+    /// Decrypts a value sealed with the key `key_id`. Rows written before
+    /// encryption was introduced have no key id and are returned unchanged.
+    pub fn open(
+        &self,
+        key_id: Option<&str>,
+        context: &str,
+        sealed: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
+        let Some(key_id) = key_id else {
+            return Ok(sealed);
+        };
+        if sealed.len() < NONCE_LENGTH {
+            return Err(anyhow!("ciphertext in {} is truncated", context).into());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+        let plaintext = self
+            .key(key_id)?
+            .cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: context.as_bytes(),
+                },
+            )
+            .map_err(|_| anyhow!("cannot decrypt {} with key {:?}", context, key_id))?;
+        Ok(plaintext)
+    }
+
+    /// `seal` for text columns: the result is base64 encoded. Empty strings
+    /// are stored as they are, so erasing a value needs no key.
+    pub fn seal_text(&self, context: &str, plaintext: &str) -> String {
+        if plaintext.is_empty() {
+            return String::new();
+        }
+        STANDARD.encode(self.seal(context, plaintext.as_bytes()))
+    }
+
+    /// `open` for values written by `seal_text`.
+    pub fn open_text(
+        &self,
+        key_id: Option<&str>,
+        context: &str,
+        stored: String,
+    ) -> Result<String, Error> {
+        if key_id.is_none() || stored.is_empty() {
+            return Ok(stored);
+        }
+        let sealed = STANDARD
+            .decode(stored)
+            .map_err(|_| anyhow!("ciphertext in {} is not base64", context))?;
+        let plaintext = self.open(key_id, context, sealed)?;
+        String::from_utf8(plaintext)
+            .map_err(|_| anyhow!("plaintext in {} is not UTF-8", context).into())
+    }
+
+    /// Keyed hash of an email address under the active key, stored next to
+    /// the ciphertext so reports can still be looked up by email.
+    pub fn blind_index(&self, email: &str) -> String {
+        Keyring::index_with(&self.keys[0], email)
+    }
+
+    /// JSON array of the blind index of `email` under every key, to match
+    /// rows whether or not they have been re-encrypted since a rotation.
+    pub fn blind_indexes(&self, email: &str) -> String {
+        let indexes: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| Keyring::index_with(key, email))
+            .collect();
+        serde_json::to_string(&indexes).unwrap()
+    }
+
+    fn index_with(key: &Key, email: &str) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&key.index_key).unwrap();
+        mac.update(email.trim().to_lowercase().as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
@@ -11,6 +11,16 @@ pub enum Error {
     Forbidden,
     #[error("NOT_FOUND")]
     NotFound,
+    #[error("UNAVAILABLE_FOR_LEGAL_REASONS")]
+    UnavailableForLegalReasons(Option<String>),
+    #[error("INVALID_TRANSITION")]
+    InvalidTransition,
+    #[error("UNDER_LEGAL_HOLD")]
+    UnderLegalHold,
+    #[error("TOO_MANY_REQUESTS")]
+    TooManyRequests,
+    #[error("VALIDATION_FAILED")]
+    Validation(Vec<FieldError>),
     #[error("INTERNAL_DB_ERROR")]
     Sqlx(sqlx::Error),
     #[error("INTERNAL_ERROR")]
@@ -23,6 +33,10 @@ impl Error {
             Self::Unauthorized => StatusCode::UNAUTHORIZED,
             Self::Forbidden => StatusCode::FORBIDDEN,
             Self::NotFound => StatusCode::NOT_FOUND,
+            Self::UnavailableForLegalReasons(_) => StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+            Self::InvalidTransition | Self::UnderLegalHold => StatusCode::CONFLICT,
+            Self::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            Self::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
             Self::Sqlx(_) | Self::Anyhow(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -33,6 +47,34 @@ pub struct ErrorMessage {
     pub msg: String,
 }
 
+/// A problem with one field of a submitted payload.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub msg: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, msg: &str) -> Self {
+        FieldError {
+            field: field.to_string(),
+            msg: msg.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ValidationErrorMessage {
+    pub msg: String,
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Serialize)]
+pub struct LegalErrorMessage {
+    pub msg: String,
+    pub reason: Option<String>,
+}
+
 impl From<sqlx::Error> for Error {
     fn from(err: sqlx::Error) -> Self {
         match err {
@@ -55,6 +97,28 @@ impl IntoResponse for Error {
                     .into_response();
             }
 
+            Self::UnavailableForLegalReasons(ref reason) => {
+                return (
+                    self.status_code(),
+                    Json(LegalErrorMessage {
+                        msg: self.to_string(),
+                        reason: reason.clone(),
+                    }),
+                )
+                    .into_response();
+            }
+
+            Self::Validation(ref errors) => {
+                return (
+                    self.status_code(),
+                    Json(ValidationErrorMessage {
+                        msg: self.to_string(),
+                        errors: errors.clone(),
+                    }),
+                )
+                    .into_response();
+            }
+
             Self::Sqlx(ref e) => {
                 tracing::error!("SQLx error: {:?}", e);
             }
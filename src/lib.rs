@@ -1,5 +1,8 @@
+pub mod crypto;
 pub mod error;
 pub mod mailer;
 pub mod models;
 pub mod resources;
 pub mod server;
+pub mod tasks;
+pub mod webhooks;
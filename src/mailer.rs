@@ -1,25 +1,47 @@
+use minijinja::{context, Value};
 use sendgrid::v3::{
-    ClickTrackingSetting, Content, Email, Message, OpenTrackingSetting, Personalization, Sender,
+    ClickTrackingSetting, Content, Email, Message, OpenTrackingSetting, Personalization,
     SubscriptionTrackingSetting, TrackingSettings,
 };
-use std::sync::{Arc, Mutex};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::Notify;
 
+use crate::crypto::Keyring;
+use crate::error::{Error, FieldError};
+use crate::models::outbox::{OutboxEntry, QueuedEmail};
+use crate::models::{
+    counter_notice::CounterNotice, digest::CategoryDigest, report::ReportCategory, DAY_MILLIS,
+};
+
+pub mod templates;
+pub mod transport;
+
+use templates::{RenderedEmail, Templates, DEFAULT_LOCALE};
+use transport::MailTransport;
+
+/// Emails delivered per claim on the outbox.
+const DELIVERY_BATCH_SIZE: i64 = 50;
+
+/// Renders emails into the outbox and delivers them from there. Every
+/// method that composes an email takes the connection of the transaction
+/// that caused it.
+#[derive(Clone)]
 pub struct Mailer {
-    sendgrid_api_key: String,
+    transport: Arc<dyn MailTransport>,
+    templates: Arc<Templates>,
+    keyring: Keyring,
     email_from: String,
     email_name: String,
-    sent: Arc<Mutex<Vec<ReportMessage>>>,
+    queued: Arc<Notify>,
 }
 
-impl Clone for Mailer {
-    fn clone(&self) -> Self {
-        Mailer {
-            sendgrid_api_key: self.sendgrid_api_key.clone(),
-            email_from: self.email_from.clone(),
-            email_name: self.email_name.clone(),
-            sent: self.sent.clone(),
-        }
-    }
+/// How a report was resolved, which decides the wording of the outcome email.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Actioned,
+    Rejected,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -27,12 +49,14 @@ pub struct ReportMessage {
     pub email_from: String,
     pub email_name: String,
     pub to: String,
+    /// The plain-text part.
     pub content: String,
+    pub html: String,
     pub subject: String,
 }
 
-impl Into<Message> for ReportMessage {
-    fn into(self) -> Message {
+impl From<ReportMessage> for Message {
+    fn from(message: ReportMessage) -> Message {
         let t = TrackingSettings {
             click_tracking: Some(ClickTrackingSetting {
                 enable: Some(false),
@@ -47,85 +71,371 @@ impl Into<Message> for ReportMessage {
             }),
         };
 
-        Message::new(Email::new(&self.email_from).set_name(&self.email_name))
-            .set_subject(&self.subject)
+        Message::new(Email::new(&message.email_from).set_name(&message.email_name))
+            .set_subject(&message.subject)
             .set_tracking_settings(t)
             .add_content(
                 Content::new()
                     .set_content_type("text/plain")
-                    .set_value(self.content),
+                    .set_value(message.content),
             )
-            .add_personalization(Personalization::new(Email::new(&self.to)))
+            .add_content(
+                Content::new()
+                    .set_content_type("text/html")
+                    .set_value(message.html),
+            )
+            .add_personalization(Personalization::new(Email::new(&message.to)))
     }
 }
 
 impl Mailer {
-    pub fn new(sendgrid_api_key: String, email_from: String, email_name: String) -> Self {
+    pub fn new(
+        transport: Arc<dyn MailTransport>,
+        templates: Templates,
+        keyring: Keyring,
+        email_from: String,
+        email_name: String,
+    ) -> Self {
         Mailer {
-            sendgrid_api_key,
+            transport,
+            templates: Arc::new(templates),
+            keyring,
             email_from,
             email_name,
-            sent: Arc::new(Mutex::from(Vec::new())),
+            queued: Arc::new(Notify::new()),
         }
     }
 
-    pub async fn respond_to(&self, to_email: &str, links: &Vec<String>) -> anyhow::Result<()> {
-        let subject = "DMCA Report Initiated";
-        let content = vec![
-            "Thanks for reaching out and initiating our DMCA Report procedure.",
-            "You reported the following links:\n",
-            &links.join("\n"),
-            "\nAn operator will get back to you within 24hrs.\n",
-            "Kind Regards,",
-            "AnonPaste Team",
-        ]
-        .join("\n");
+    fn render(
+        &self,
+        template: &str,
+        locale: &str,
+        ctx: Value,
+    ) -> Result<RenderedEmail, minijinja::Error> {
+        self.templates.render(
+            template,
+            context! { instance_name => &self.email_name, locale, ..ctx },
+        )
+    }
+
+    async fn queue(
+        &self,
+        conn: &mut SqliteConnection,
+        to_email: &str,
+        locale: &str,
+        template: &str,
+        ctx: Value,
+    ) -> Result<(), Error> {
+        let email = self
+            .render(template, locale, ctx)
+            .map_err(anyhow::Error::from)?;
+        OutboxEntry::enqueue(conn, &self.keyring, template, to_email, &email).await?;
+        Ok(())
+    }
+
+    async fn deliver(&self, pool: &SqlitePool, only_new: bool) -> Result<u64, Error> {
+        let mut delivered = 0;
+        loop {
+            let emails =
+                OutboxEntry::claim(pool, &self.keyring, only_new, DELIVERY_BATCH_SIZE).await?;
+            if emails.is_empty() {
+                return Ok(delivered);
+            }
+            for email in emails {
+                match self.transport.send(&self.message(&email)).await {
+                    Ok(()) => {
+                        OutboxEntry::delivered(pool, email.id).await?;
+                        delivered += 1;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to deliver email {}: {:?}", email.id, e);
+                        OutboxEntry::failed(pool, &email, &format!("{:#}", e)).await?;
+                    }
+                }
+            }
+        }
+    }
 
-        let message = ReportMessage {
+    fn message(&self, email: &QueuedEmail) -> ReportMessage {
+        ReportMessage {
             email_from: self.email_from.to_owned(),
             email_name: self.email_name.to_owned(),
-            to: to_email.to_string(),
-            subject: subject.to_string(),
-            content,
-        };
+            to: email.recipient.clone(),
+            content: email.content.clone(),
+            html: email.html.clone(),
+            subject: email.subject.clone(),
+        }
+    }
 
-        if self.sendgrid_api_key != "TEST" {
-            Sender::new(self.sendgrid_api_key.clone())
-                .send(&message.into())
-                .await?;
-        } else {
-            let sent_vec = &mut self.sent.lock().unwrap();
-            (*sent_vec).push(message);
+    /// Delivers every queued email that is due, including retries. Returns
+    /// the number delivered.
+    pub async fn deliver_due(&self, pool: &SqlitePool) -> Result<u64, Error> {
+        self.deliver(pool, false).await
+    }
+
+    /// Wakes the outbox worker once a transaction that queued emails has
+    /// committed, so they go out right away without holding up the request.
+    pub fn notify(&self) {
+        self.queued.notify_one();
+    }
+
+    /// Waits for the next `notify`.
+    pub async fn notified(&self) {
+        self.queued.notified().await;
+    }
+
+    /// Delivers emails that have not been tried yet. Failures stay in the
+    /// outbox to be retried once they are due.
+    pub async fn flush(&self, pool: &SqlitePool) {
+        if let Err(e) = self.deliver(pool, true).await {
+            tracing::error!("Failed to flush the email outbox: {:?}", e);
         }
+    }
 
-        let content = format!(
-            "These links have been reported by {}:\n\n{}\n\nAnonPaste Team",
-            to_email,
-            links.join("\n")
-        );
+    /// Picks the supported locale closest to a language tag or an
+    /// `Accept-Language` header, or English.
+    pub fn negotiate_locale(&self, requested: Option<&str>) -> String {
+        self.templates.negotiate(requested)
+    }
 
-        let message = ReportMessage {
-            email_from: self.email_from.to_owned(),
-            email_name: self.email_name.to_owned(),
-            to: self.email_from.to_owned(),
-            subject: subject.to_string(),
-            content,
+    /// How a category is named in emails, e.g. "DMCA Report".
+    pub fn category_label(&self, locale: &str, category: ReportCategory) -> String {
+        self.templates
+            .message(locale, &format!("category.{}", category.as_str()), &[])
+    }
+
+    /// The subject of replies on a report, which carries the ticket id so
+    /// the reporter's answers find their way back.
+    pub fn reply_subject(&self, locale: &str, category: ReportCategory, ticket_id: &str) -> String {
+        let category = self.category_label(locale, category);
+        self.templates.message(
+            locale,
+            "reply_subject",
+            &[("category", &category), ("ticket_id", ticket_id)],
+        )
+    }
+
+    /// Variables describing the report's category.
+    fn category_context(&self, locale: &str, category: ReportCategory) -> Value {
+        context! {
+            category => self.category_label(locale, category),
+            sla => self.templates.message(locale, &format!("sla.{}", category.as_str()), &[]),
+        }
+    }
+
+    /// Renders a template with made-up variables, so operators can check
+    /// their wording before a reporter sees it.
+    pub fn preview(&self, template: &str, locale: Option<&str>) -> Result<RenderedEmail, Error> {
+        let ctx = templates::sample_context(template).ok_or(Error::NotFound)?;
+        let locale = self.negotiate_locale(locale);
+        let category = ReportCategory::Dmca;
+        let subject = self.reply_subject(&locale, category, "AP-7K2M9Q");
+        let ctx = context! {
+            ..context! { subject },
+            ..ctx,
+            ..self.category_context(&locale, category)
         };
+        self.render(template, &locale, ctx)
+            .map_err(|e| Error::Validation(vec![FieldError::new("template", &e.to_string())]))
+    }
+
+    /// Asks the reporter to confirm their address before anyone else hears
+    /// about the report.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn confirm_report(
+        &self,
+        conn: &mut SqliteConnection,
+        to_email: &str,
+        locale: &str,
+        ticket_id: &str,
+        links: &[String],
+        category: ReportCategory,
+        confirmation_link: &str,
+    ) -> Result<(), Error> {
+        self.queue(
+            conn,
+            to_email,
+            locale,
+            "confirm_report",
+            context! {
+                links,
+                ticket_id,
+                confirmation_link,
+                ..self.category_context(locale, category)
+            },
+        )
+        .await
+    }
 
-        if self.sendgrid_api_key != "TEST" {
-            Sender::new(self.sendgrid_api_key.to_string())
-                .send(&message.into())
-                .await?;
-        } else {
-            let sent_vec = &mut self.sent.lock().unwrap();
-            (*sent_vec).push(message);
+    /// Acknowledges a confirmed report to the reporter.
+    pub async fn respond_to(
+        &self,
+        conn: &mut SqliteConnection,
+        to_email: &str,
+        locale: &str,
+        links: &Vec<String>,
+        category: ReportCategory,
+    ) -> Result<(), Error> {
+        self.queue(
+            conn,
+            to_email,
+            locale,
+            "report_received",
+            context! { links, ..self.category_context(locale, category) },
+        )
+        .await
+    }
+
+    /// The operators to write to about a category: its recipients, or our
+    /// own address when it has none.
+    fn operators<'a>(&'a self, operators: &'a [String]) -> Vec<&'a str> {
+        match operators {
+            [] => vec![self.email_from.as_str()],
+            operators => operators.iter().map(String::as_str).collect(),
         }
+    }
 
+    /// Forwards a confirmed report to the category's operators. Operators
+    /// are written to in English.
+    pub async fn forward_report(
+        &self,
+        conn: &mut SqliteConnection,
+        reporter: &str,
+        links: &Vec<String>,
+        category: ReportCategory,
+        operators: &[String],
+    ) -> Result<(), Error> {
+        for operator in self.operators(operators) {
+            self.queue(
+                &mut *conn,
+                operator,
+                DEFAULT_LOCALE,
+                "report_forwarded",
+                context! {
+                    links,
+                    reporter,
+                    ..self.category_context(DEFAULT_LOCALE, category)
+                },
+            )
+            .await?;
+        }
         Ok(())
     }
 
+    /// Sends each operator the digest of the categories they look after.
+    pub async fn send_digest(
+        &self,
+        conn: &mut SqliteConnection,
+        date: &str,
+        categories: &[CategoryDigest],
+        recipients: &HashMap<ReportCategory, Vec<String>>,
+    ) -> Result<(), Error> {
+        let operators = |category| {
+            let operators = recipients
+                .get(&category)
+                .map(|operators| operators.as_slice())
+                .unwrap_or_default();
+            self.operators(operators)
+        };
+        // Every operator gets a digest, even when their queue is empty.
+        let mut digests: BTreeMap<&str, Vec<&CategoryDigest>> = BTreeMap::new();
+        for category in ReportCategory::ALL {
+            for operator in operators(category) {
+                digests.entry(operator).or_default();
+            }
+        }
+        for section in categories {
+            for operator in operators(section.category) {
+                digests.entry(operator).or_default().push(section);
+            }
+        }
+        for (operator, categories) in digests {
+            self.queue(
+                &mut *conn,
+                operator,
+                DEFAULT_LOCALE,
+                "moderation_digest",
+                context! { date, categories },
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Tells the reporter how their report was resolved.
+    pub async fn notify_outcome(
+        &self,
+        conn: &mut SqliteConnection,
+        to_email: &str,
+        locale: &str,
+        links: &[String],
+        category: ReportCategory,
+        outcome: Outcome,
+    ) -> Result<(), Error> {
+        let outcome = match outcome {
+            Outcome::Actioned => "actioned",
+            Outcome::Rejected => "rejected",
+        };
+        self.queue(
+            conn,
+            to_email,
+            locale,
+            "report_outcome",
+            context! {
+                links,
+                outcome,
+                ..self.category_context(locale, category)
+            },
+        )
+        .await
+    }
+
+    /// Forwards a counter-notice to the reporter whose report led to the
+    /// takedown, so they can decide whether to take legal action.
+    pub async fn forward_counter_notice(
+        &self,
+        conn: &mut SqliteConnection,
+        to_email: &str,
+        locale: &str,
+        links: &[String],
+        notice: &CounterNotice,
+    ) -> Result<(), Error> {
+        self.queue(
+            conn,
+            to_email,
+            locale,
+            "counter_notice",
+            context! {
+                links,
+                notice,
+                restore_days => (notice.restore_after - notice.created_at) / DAY_MILLIS,
+            },
+        )
+        .await
+    }
+
+    /// Sends an operator's reply to the reporter.
+    pub async fn reply_to_reporter(
+        &self,
+        conn: &mut SqliteConnection,
+        to_email: &str,
+        locale: &str,
+        subject: &str,
+        body: &str,
+    ) -> Result<(), Error> {
+        self.queue(
+            conn,
+            to_email,
+            locale,
+            "reporter_reply",
+            context! { subject, body },
+        )
+        .await
+    }
+
+    /// Messages captured by the in-memory transport. Always empty with any
+    /// other transport.
     pub fn get_sent_emails(&self) -> Vec<ReportMessage> {
-        let sent_vec = &self.sent.lock().unwrap();
-        sent_vec.to_vec()
+        self.transport.captured()
     }
 }
@@ -0,0 +1,307 @@
+use anyhow::Context;
+use minijinja::{context, Environment, ErrorKind, State, Value};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The locale every message exists in, used when no other one fits.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Emails the mailer sends. Each one is rendered from a `<name>.subject`, a
+/// `<name>.txt` and a `<name>.html` template.
+pub const TEMPLATES: &[&str] = &[
+    "confirm_report",
+    "report_received",
+    "report_forwarded",
+    "report_outcome",
+    "counter_notice",
+    "reporter_reply",
+    "moderation_digest",
+];
+
+macro_rules! builtin {
+    ($($file:literal),* $(,)?) => {
+        &[$(($file, include_str!(concat!("../../templates/email/", $file)))),*]
+    };
+}
+
+/// The templates shipped with the server, used for any file missing from
+/// the configured directory.
+const BUILTIN: &[(&str, &str)] = builtin![
+    "layout.html",
+    "confirm_report.subject",
+    "confirm_report.txt",
+    "confirm_report.html",
+    "report_received.subject",
+    "report_received.txt",
+    "report_received.html",
+    "report_forwarded.subject",
+    "report_forwarded.txt",
+    "report_forwarded.html",
+    "report_outcome.subject",
+    "report_outcome.txt",
+    "report_outcome.html",
+    "counter_notice.subject",
+    "counter_notice.txt",
+    "counter_notice.html",
+    "reporter_reply.subject",
+    "reporter_reply.txt",
+    "reporter_reply.html",
+    "moderation_digest.subject",
+    "moderation_digest.txt",
+    "moderation_digest.html",
+];
+
+/// The message catalogues shipped with the server.
+const BUILTIN_LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../../locales/en.json")),
+    ("de", include_str!("../../locales/de.json")),
+    ("es", include_str!("../../locales/es.json")),
+    ("fr", include_str!("../../locales/fr.json")),
+    ("it", include_str!("../../locales/it.json")),
+];
+
+/// Messages by key, for each locale.
+type Catalogues = HashMap<String, HashMap<String, String>>;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+/// Email templates in Jinja syntax. The HTML parts are autoescaped.
+///
+/// Templates hold no wording of their own: `t("key")` looks the key up in
+/// the message catalogue of the `locale` being rendered, falling back to
+/// English, and fills `{name}` placeholders from the template's variables.
+pub struct Templates {
+    env: Environment<'static>,
+    catalogues: Arc<Catalogues>,
+}
+
+/// Fills the `{name}` placeholders of a message.
+fn interpolate(
+    message: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, minijinja::Error> {
+    let mut result = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 1..start + end];
+        let value = lookup(name).ok_or_else(|| {
+            minijinja::Error::new(
+                ErrorKind::UndefinedError,
+                format!("no value for {{{}}} in {:?}", name, message),
+            )
+        })?;
+        result.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn message<'a>(catalogues: &'a Catalogues, locale: &str, key: &str) -> Option<&'a str> {
+    catalogues
+        .get(locale)
+        .and_then(|messages| messages.get(key))
+        .or_else(|| catalogues.get(DEFAULT_LOCALE)?.get(key))
+        .map(String::as_str)
+}
+
+/// Loads the built-in catalogues, merging in any `locales/<locale>.json` in
+/// `dir`. Those may override some messages or add a whole new locale.
+fn load_catalogues(dir: Option<&Path>) -> anyhow::Result<Catalogues> {
+    let mut catalogues = Catalogues::new();
+    for (locale, source) in BUILTIN_LOCALES {
+        let messages = serde_json::from_str(source)
+            .with_context(|| format!("invalid built-in catalogue {}", locale))?;
+        catalogues.insert(locale.to_string(), messages);
+    }
+    let Some(dir) = dir
+        .map(|dir| dir.join("locales"))
+        .filter(|dir| dir.is_dir())
+    else {
+        return Ok(catalogues);
+    };
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(locale) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("cannot read {}", path.display()))?;
+        let messages: HashMap<String, String> = serde_json::from_str(&source)
+            .with_context(|| format!("invalid catalogue {}", path.display()))?;
+        catalogues
+            .entry(locale.to_lowercase())
+            .or_default()
+            .extend(messages);
+    }
+    Ok(catalogues)
+}
+
+impl Templates {
+    /// Loads the built-in templates and catalogues, replacing each template
+    /// that has a file of the same name in `dir`.
+    pub fn load(dir: Option<&Path>) -> anyhow::Result<Templates> {
+        let catalogues = Arc::new(load_catalogues(dir)?);
+        let mut env = Environment::new();
+        let lookup = catalogues.clone();
+        env.add_function(
+            "t",
+            move |state: &State, key: &str| -> Result<String, minijinja::Error> {
+                let locale = state.lookup("locale");
+                let locale = locale
+                    .as_ref()
+                    .and_then(|locale| locale.as_str())
+                    .unwrap_or(DEFAULT_LOCALE);
+                let text = message(&lookup, locale, key).ok_or_else(|| {
+                    minijinja::Error::new(
+                        ErrorKind::UndefinedError,
+                        format!("no message {:?} in the catalogues", key),
+                    )
+                })?;
+                interpolate(text, |name| {
+                    state
+                        .lookup(name)
+                        .filter(|value| !value.is_undefined())
+                        .map(|value| value.to_string())
+                })
+            },
+        );
+        for (name, builtin) in BUILTIN {
+            let source = match dir.map(|dir| dir.join(name)) {
+                Some(path) if path.exists() => std::fs::read_to_string(&path)
+                    .with_context(|| format!("cannot read {}", path.display()))?,
+                _ => builtin.to_string(),
+            };
+            env.add_template_owned(*name, source)
+                .with_context(|| format!("invalid email template {}", name))?;
+        }
+        Ok(Templates { env, catalogues })
+    }
+
+    /// Looks up a message outside of a template, filling its placeholders
+    /// from `vars`.
+    pub fn message(&self, locale: &str, key: &str, vars: &[(&str, &str)]) -> String {
+        let text = message(&self.catalogues, locale, key).unwrap_or(key);
+        interpolate(text, |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })
+        .unwrap_or_else(|_| text.to_string())
+    }
+
+    /// Picks the locale to write to a reporter in, from a language tag such
+    /// as `de-AT` or a whole `Accept-Language` header. Tags are tried in
+    /// order of preference, and a regional tag matches its language.
+    pub fn negotiate(&self, requested: Option<&str>) -> String {
+        let mut tags: Vec<(f32, String)> = requested
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let tag = parts.next()?.trim().to_lowercase().replace('_', "-");
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map(|q| q.trim().parse().unwrap_or(0.0))
+                    .unwrap_or(1.0);
+                (!tag.is_empty() && quality > 0.0).then_some((quality, tag))
+            })
+            .collect();
+        tags.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (_, tag) in tags {
+            let language = tag.split('-').next().unwrap_or_default();
+            for candidate in [tag.as_str(), language] {
+                if self.catalogues.contains_key(candidate) {
+                    return candidate.to_string();
+                }
+            }
+        }
+        DEFAULT_LOCALE.to_string()
+    }
+
+    pub fn render(&self, name: &str, ctx: Value) -> Result<RenderedEmail, minijinja::Error> {
+        let part = |suffix: &str| {
+            self.env
+                .get_template(&format!("{}.{}", name, suffix))?
+                .render(&ctx)
+        };
+        Ok(RenderedEmail {
+            subject: part("subject")?.trim().to_string(),
+            text: part("txt")?,
+            html: part("html")?,
+        })
+    }
+}
+
+/// Made-up variables to preview a template with. The category is always a
+/// DMCA Report, whose wording the mailer adds in the requested locale.
+pub fn sample_context(name: &str) -> Option<Value> {
+    let links = vec![
+        "https://anonpaste.example/a1b2c3#key",
+        "https://anonpaste.example/d4e5f6#key",
+    ];
+    let ctx = match name {
+        "confirm_report" => context! {
+            links,
+            ticket_id => "AP-7K2M9Q",
+            confirmation_link => "https://anonpaste.example/report/verify/sample-token",
+        },
+        "report_received" => context! { links },
+        "report_forwarded" => context! {
+            links,
+            reporter => "reporter@example.com",
+        },
+        "report_outcome" => context! {
+            links,
+            outcome => "actioned",
+        },
+        "counter_notice" => context! {
+            links,
+            notice => context! {
+                name => "Jane Uploader",
+                email => "jane@example.com",
+                address => "1 Example Street, Springfield",
+                statement => "I swear under penalty of perjury that the content was removed by mistake.",
+                signature => "Jane Uploader",
+            },
+            restore_days => 14,
+        },
+        "reporter_reply" => context! {
+            ticket_id => "AP-7K2M9Q",
+            body => "Could you tell us which part of the paste is yours?",
+        },
+        "moderation_digest" => context! {
+            date => "2026-10-19",
+            categories => vec![context! {
+                category => "dmca",
+                overdue => vec![context! {
+                    ticket_id => "AP-7K2M9Q",
+                    status => "in_review",
+                    link => "https://anonpaste.example/admin/reports/12",
+                }],
+                new => vec![context! {
+                    ticket_id => "AP-3H8W1D",
+                    status => "open",
+                    link => "https://anonpaste.example/admin/reports/15",
+                }],
+                open => Vec::<Value>::new(),
+            }],
+        },
+        _ => return None,
+    };
+    Some(ctx)
+}
@@ -1,7 +1,14 @@
-use anonpaste::server::{run_server, Config};
-use anyhow::Context;
+use anonpaste::crypto::Keyring;
+use anonpaste::mailer::transport::MailTransportConfig;
+use anonpaste::models::digest::DigestSettings;
+use anonpaste::models::report::ReportCategory;
+use anonpaste::server::{reencrypt_reports, run_server, Config};
+use anyhow::{bail, Context};
+use chrono::NaiveTime;
 
+use std::collections::HashMap;
 use std::env;
+use std::path::PathBuf;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -11,20 +18,135 @@ async fn main() -> anyhow::Result<()> {
     let frontend_origin =
         env::var("FRONTEND_ORIGIN").context("Please provide a FRONTEND_ORIGIN")?;
     let admin_token = env::var("ADMIN_TOKEN").context("Please provide an ADMIN_TOKEN")?;
-    let sendgrid_api_key =
-        env::var("SENDGRID_API_KEY").context("Please provide an SENDGRID_API_KEY")?;
+    let mail_transport = match env::var("MAIL_TRANSPORT").as_deref() {
+        Ok("sendgrid") | Err(_) => MailTransportConfig::SendGrid {
+            api_key: env::var("SENDGRID_API_KEY").context("Please provide an SENDGRID_API_KEY")?,
+        },
+        Ok("smtp") => MailTransportConfig::Smtp {
+            url: env::var("SMTP_URL").context("Please provide an SMTP_URL")?,
+        },
+        Ok("maildir") => MailTransportConfig::Maildir {
+            path: env::var("MAILDIR_PATH")
+                .context("Please provide a MAILDIR_PATH")?
+                .into(),
+        },
+        Ok("memory") => MailTransportConfig::Memory,
+        Ok(transport) => bail!(
+            "Unknown MAIL_TRANSPORT {}, expected sendgrid, smtp, maildir or memory",
+            transport
+        ),
+    };
     let email_from = env::var("EMAIL_FROM").context("Please provide an EMAIL_FROM")?;
     let email_name = env::var("EMAIL_NAME").context("Please provide an EMAIL_NAME")?;
+    let email_templates_dir = env::var("EMAIL_TEMPLATES_DIR").ok().map(PathBuf::from);
+    let report_encryption_keys = env::var("REPORT_ENCRYPTION_KEYS")
+        .context("Please provide REPORT_ENCRYPTION_KEYS")
+        .and_then(|keys| Keyring::parse(&keys))
+        .context("REPORT_ENCRYPTION_KEYS must look like id:base64key,id:base64key")?;
+    let trash_retention_days = match env::var("TRASH_RETENTION_DAYS") {
+        Ok(days) => days
+            .parse()
+            .context("TRASH_RETENTION_DAYS must be a number of days")?,
+        Err(_) => 30,
+    };
+    let auto_hide_report_threshold = env::var("AUTO_HIDE_REPORT_THRESHOLD")
+        .ok()
+        .map(|threshold| threshold.parse())
+        .transpose()
+        .context("AUTO_HIDE_REPORT_THRESHOLD must be a number of reports")?;
+    let mut report_recipients = HashMap::new();
+    for category in ReportCategory::ALL {
+        let key = format!("REPORT_RECIPIENTS_{}", category.as_str().to_uppercase());
+        if let Ok(recipients) = env::var(&key) {
+            let recipients = recipients
+                .split(',')
+                .map(|recipient| recipient.trim().to_string())
+                .filter(|recipient| !recipient.is_empty())
+                .collect();
+            report_recipients.insert(category, recipients);
+        }
+    }
+    let operator_report_emails = match env::var("OPERATOR_REPORT_EMAILS").as_deref() {
+        Ok("true") | Err(_) => true,
+        Ok("false") => false,
+        Ok(_) => bail!("OPERATOR_REPORT_EMAILS must be true or false"),
+    };
+    let digest = match env::var("DIGEST_TIME") {
+        Ok(time) => Some(DigestSettings {
+            time: NaiveTime::parse_from_str(&time, "%H:%M")
+                .context("DIGEST_TIME must be a time of day like 08:30")?,
+            timezone: match env::var("DIGEST_TIMEZONE") {
+                Ok(timezone) => timezone
+                    .parse()
+                    .map_err(anyhow::Error::msg)
+                    .context("DIGEST_TIMEZONE must be a timezone like Europe/Berlin")?,
+                Err(_) => chrono_tz::UTC,
+            },
+            report_url: env::var("DIGEST_REPORT_URL").unwrap_or_else(|_| {
+                format!(
+                    "{}/admin/reports/{{id}}",
+                    frontend_origin.trim_end_matches('/')
+                )
+            }),
+        }),
+        Err(_) => None,
+    };
+    let counter_notice_waiting_days = match env::var("COUNTER_NOTICE_WAITING_DAYS") {
+        Ok(days) => days
+            .parse()
+            .context("COUNTER_NOTICE_WAITING_DAYS must be a number of days")?,
+        Err(_) => 14,
+    };
+    let report_verification_hours = match env::var("REPORT_VERIFICATION_HOURS") {
+        Ok(hours) => hours
+            .parse()
+            .context("REPORT_VERIFICATION_HOURS must be a number of hours")?,
+        Err(_) => 24,
+    };
+    let report_retention_days = match env::var("REPORT_RETENTION_DAYS") {
+        Ok(days) => days
+            .parse()
+            .context("REPORT_RETENTION_DAYS must be a number of days")?,
+        Err(_) => 90,
+    };
+    let webhook_urls: Vec<String> = env::var("WEBHOOK_URLS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect();
+    let webhook_secret = match env::var("WEBHOOK_SECRET") {
+        Ok(secret) => secret,
+        Err(_) if webhook_urls.is_empty() => String::new(),
+        Err(_) => bail!("Please provide a WEBHOOK_SECRET to sign webhooks with"),
+    };
 
-    run_server(Config {
+    let config = Config {
         db_url,
         frontend_origin,
         admin_token,
-        sendgrid_api_key,
+        mail_transport,
         email_from,
         email_name,
-    })
-    .await?;
+        email_templates_dir,
+        report_encryption_keys,
+        trash_retention_days,
+        auto_hide_report_threshold,
+        report_recipients,
+        operator_report_emails,
+        digest,
+        counter_notice_waiting_days,
+        report_verification_hours,
+        report_retention_days,
+        webhook_urls,
+        webhook_secret,
+    };
+
+    match env::args().nth(1).as_deref() {
+        None => run_server(config).await?,
+        Some("reencrypt") => reencrypt_reports(config).await?,
+        Some(command) => bail!("Unknown command {}, expected reencrypt", command),
+    }
 
     Ok(())
 }
@@ -0,0 +1,233 @@
+use crate::crypto::Keyring;
+use crate::error::{Error, FieldError};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+pub const MAX_ATTACHMENTS: usize = 3;
+pub const MAX_ATTACHMENT_SIZE: usize = 2 * 1024 * 1024;
+const MAX_FILENAME_LENGTH: usize = 255;
+const CONTENT_CONTEXT: &str = "report_attachment.content";
+const ALLOWED_CONTENT_TYPES: [&str; 6] = [
+    "application/pdf",
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "text/plain",
+];
+
+/// Evidence submitted with a report, such as a signed notice or a screenshot.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAttachment {
+    pub filename: String,
+    pub content_type: String,
+    /// Base64 encoded file content.
+    pub content: String,
+}
+
+/// An attachment that passed validation, decoded and ready to be stored.
+pub(crate) struct ValidAttachment {
+    filename: String,
+    content_type: String,
+    content: Vec<u8>,
+}
+
+/// Attachment metadata as listed on a report. The content itself is only
+/// served by `ReportAttachment::content`.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportAttachment {
+    pub id: i64,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    pub created_at: i64,
+}
+
+pub struct AttachmentContent {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+/// Checks the count, type and decoded size of each attachment, pushing a
+/// `FieldError` for every problem found.
+pub(crate) fn validate_attachments(
+    attachments: Vec<CreateAttachment>,
+    errors: &mut Vec<FieldError>,
+) -> Vec<ValidAttachment> {
+    if attachments.len() > MAX_ATTACHMENTS {
+        errors.push(FieldError::new("attachments", "TOO_MANY_ATTACHMENTS"));
+        return Vec::new();
+    }
+    let mut valid = Vec::new();
+    for (i, attachment) in attachments.into_iter().enumerate() {
+        let field = format!("attachments[{}]", i);
+        let content_type = attachment.content_type.trim().to_lowercase();
+        if !ALLOWED_CONTENT_TYPES.contains(&content_type.as_str()) {
+            errors.push(FieldError::new(&field, "UNSUPPORTED_ATTACHMENT_TYPE"));
+            continue;
+        }
+        // Base64 is a third larger than the data it encodes, so oversized
+        // files are rejected before decoding them.
+        if attachment.content.len() / 4 * 3 > MAX_ATTACHMENT_SIZE + 2 {
+            errors.push(FieldError::new(&field, "ATTACHMENT_TOO_LARGE"));
+            continue;
+        }
+        let content = match STANDARD.decode(attachment.content.trim()) {
+            Ok(content) if content.len() > MAX_ATTACHMENT_SIZE => {
+                errors.push(FieldError::new(&field, "ATTACHMENT_TOO_LARGE"));
+                continue;
+            }
+            Ok(content) if content.is_empty() => {
+                errors.push(FieldError::new(&field, "EMPTY_ATTACHMENT"));
+                continue;
+            }
+            Ok(content) => content,
+            Err(_) => {
+                errors.push(FieldError::new(&field, "INVALID_ATTACHMENT"));
+                continue;
+            }
+        };
+        valid.push(ValidAttachment {
+            filename: sanitize_filename(&attachment.filename),
+            content_type,
+            content,
+        });
+    }
+    valid
+}
+
+/// Keeps only the last path component and drops characters that could
+/// break out of a `Content-Disposition` header.
+fn sanitize_filename(filename: &str) -> String {
+    let name: String = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(MAX_FILENAME_LENGTH)
+        .collect();
+    match name.trim() {
+        "" => "attachment".to_string(),
+        name => name.to_string(),
+    }
+}
+
+impl ReportAttachment {
+    pub(crate) async fn insert(
+        conn: &mut SqliteConnection,
+        keyring: &Keyring,
+        report_id: i64,
+        attachments: &[ValidAttachment],
+        created_at: i64,
+    ) -> Result<(), Error> {
+        let key_id = keyring.active_key_id();
+        for attachment in attachments {
+            let size = attachment.content.len() as i64;
+            let content = keyring.seal(CONTENT_CONTEXT, &attachment.content);
+            sqlx::query!(
+                "INSERT INTO report_attachment ( report_id, filename, content_type, size, content, created_at, key_id )
+                    VALUES ( ?1, ?2, ?3, ?4, ?5, ?6, ?7 )",
+                report_id,
+                attachment.filename,
+                attachment.content_type,
+                size,
+                content,
+                created_at,
+                key_id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn for_report(
+        pool: &SqlitePool,
+        report_id: i64,
+    ) -> Result<Vec<ReportAttachment>, Error> {
+        let attachments = sqlx::query_as!(
+            ReportAttachment,
+            r#"SELECT id AS "id!", filename, content_type, size, created_at
+                FROM report_attachment WHERE report_id = ? ORDER BY id"#,
+            report_id
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(attachments)
+    }
+
+    /// Reads an attachment of a live report. Attachments of trashed reports
+    /// are unavailable until the report is restored.
+    pub async fn content(
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        report_id: i64,
+        id: i64,
+    ) -> Result<AttachmentContent, Error> {
+        let attachment = sqlx::query!(
+            r#"SELECT report_attachment.filename,
+                      report_attachment.content_type,
+                      report_attachment.content,
+                      report_attachment.key_id
+                FROM report_attachment
+                JOIN report ON report.id = report_attachment.report_id
+                WHERE report_attachment.id = ?1
+                    AND report_attachment.report_id = ?2
+                    AND report.deleted_at IS NULL
+                    AND report.verified_at IS NOT NULL"#,
+            id,
+            report_id
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(AttachmentContent {
+            filename: attachment.filename,
+            content_type: attachment.content_type,
+            content: keyring.open(
+                attachment.key_id.as_deref(),
+                CONTENT_CONTEXT,
+                attachment.content,
+            )?,
+        })
+    }
+
+    /// Re-encrypts up to `limit` attachments not yet encrypted with the
+    /// active key. Returns how many were rewritten.
+    pub(crate) async fn reencrypt_batch(
+        conn: &mut SqliteConnection,
+        keyring: &Keyring,
+        limit: i64,
+    ) -> Result<u64, Error> {
+        let key_id = keyring.active_key_id();
+        let attachments = sqlx::query!(
+            r#"SELECT id AS "id!", content, key_id
+                FROM report_attachment WHERE key_id IS NOT ?1 LIMIT ?2"#,
+            key_id,
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        for attachment in &attachments {
+            let content = keyring.open(
+                attachment.key_id.as_deref(),
+                CONTENT_CONTEXT,
+                attachment.content.clone(),
+            )?;
+            let content = keyring.seal(CONTENT_CONTEXT, &content);
+            sqlx::query!(
+                "UPDATE report_attachment SET content = ?1, key_id = ?2 WHERE id = ?3",
+                content,
+                key_id,
+                attachment.id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+        Ok(attachments.len() as u64)
+    }
+}
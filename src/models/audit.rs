@@ -0,0 +1,178 @@
+use crate::error::Error;
+use crate::models::{now_millis, Page};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// The `prev_hash` of the very first entry in the chain.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Who is performing an admin action, and why.
+#[derive(Clone, Debug)]
+pub struct AuditContext {
+    pub actor: String,
+    pub reason: Option<String>,
+}
+
+impl AuditContext {
+    /// Context for actions taken by background tasks rather than an operator.
+    pub fn system(reason: &str) -> Self {
+        AuditContext {
+            actor: "system".to_string(),
+            reason: Some(reason.to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListAudit {
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor: String,
+    pub action: String,
+    pub target_id: String,
+    pub reason: Option<String>,
+    pub created_at: i64,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditVerification {
+    pub valid: bool,
+    pub checked: i64,
+    pub broken_at: Option<i64>,
+}
+
+fn entry_hash(
+    prev_hash: &str,
+    actor: &str,
+    action: &str,
+    target_id: &str,
+    reason: &Option<String>,
+    created_at: i64,
+) -> String {
+    let fields = serde_json::to_vec(&(actor, action, target_id, reason, created_at)).unwrap();
+    let mut hasher = Sha256::new();
+    hasher.update(prev_hash.as_bytes());
+    hasher.update(fields);
+    hex::encode(hasher.finalize())
+}
+
+impl AuditEntry {
+    /// Appends an entry to the chain. Callers pass the connection of the
+    /// transaction that performs the audited change, so both land together.
+    pub async fn append(
+        conn: &mut SqliteConnection,
+        ctx: &AuditContext,
+        action: &str,
+        target_id: &str,
+    ) -> Result<(), Error> {
+        let prev_hash = sqlx::query_scalar!("SELECT hash FROM audit_log ORDER BY id DESC LIMIT 1")
+            .fetch_optional(&mut *conn)
+            .await?
+            .unwrap_or_else(|| GENESIS_HASH.to_string());
+        let created_at = now_millis();
+        let hash = entry_hash(
+            &prev_hash,
+            &ctx.actor,
+            action,
+            target_id,
+            &ctx.reason,
+            created_at,
+        );
+
+        sqlx::query!(
+            "INSERT INTO audit_log ( actor, action, target_id, reason, created_at, prev_hash, hash )
+                VALUES ( ?1, ?2, ?3, ?4, ?5, ?6, ?7 )",
+            ctx.actor,
+            action,
+            target_id,
+            ctx.reason,
+            created_at,
+            prev_hash,
+            hash
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list(pool: &SqlitePool, query: ListAudit) -> Result<Page<AuditEntry>, Error> {
+        let cursor = query.cursor.unwrap_or(0);
+        let limit = query.limit.unwrap_or(50).clamp(1, 500);
+        let items = sqlx::query_as!(
+            AuditEntry,
+            "SELECT id, actor, action, target_id, reason, created_at, prev_hash, hash
+                FROM audit_log WHERE id > ?1 ORDER BY id LIMIT ?2",
+            cursor,
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let next_cursor = match items.last() {
+            Some(last) if items.len() as i64 == limit => Some(last.id),
+            _ => None,
+        };
+        Ok(Page { items, next_cursor })
+    }
+
+    /// Walks the whole chain and recomputes every hash, reporting the first
+    /// entry that does not match.
+    pub async fn verify(pool: &SqlitePool) -> Result<AuditVerification, Error> {
+        let mut expected_prev = GENESIS_HASH.to_string();
+        let mut checked = 0;
+        let mut cursor = None;
+
+        loop {
+            let page = AuditEntry::list(
+                pool,
+                ListAudit {
+                    cursor,
+                    limit: Some(500),
+                },
+            )
+            .await?;
+
+            for entry in page.items {
+                let hash = entry_hash(
+                    &entry.prev_hash,
+                    &entry.actor,
+                    &entry.action,
+                    &entry.target_id,
+                    &entry.reason,
+                    entry.created_at,
+                );
+                if entry.prev_hash != expected_prev || entry.hash != hash {
+                    return Ok(AuditVerification {
+                        valid: false,
+                        checked,
+                        broken_at: Some(entry.id),
+                    });
+                }
+                expected_prev = entry.hash;
+                checked += 1;
+            }
+
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(AuditVerification {
+            valid: true,
+            checked,
+            broken_at: None,
+        })
+    }
+}
@@ -0,0 +1,428 @@
+use crate::crypto::Keyring;
+use crate::error::{Error, FieldError};
+use crate::mailer::Mailer;
+use crate::models::audit::{AuditContext, AuditEntry};
+use crate::models::paste::PasteStatus;
+use crate::models::report::{
+    is_valid_email, ReportSettings, EMAIL_CONTEXT as REPORT_EMAIL_CONTEXT,
+};
+use crate::models::{now_millis, DAY_MILLIS};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+const MAX_FIELD_LENGTH: usize = 10_000;
+const NAME_CONTEXT: &str = "counter_notice.name";
+const EMAIL_CONTEXT: &str = "counter_notice.email";
+const ADDRESS_CONTEXT: &str = "counter_notice.address";
+const STATEMENT_CONTEXT: &str = "counter_notice.statement";
+const SIGNATURE_CONTEXT: &str = "counter_notice.signature";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCounterNotice {
+    pub name: String,
+    pub email: String,
+    pub address: String,
+    /// Statement under penalty of perjury that the content was removed by
+    /// mistake or misidentification.
+    pub statement: String,
+    pub consent_to_jurisdiction: bool,
+    pub signature: String,
+}
+
+impl CreateCounterNotice {
+    fn validate(self) -> Result<CreateCounterNotice, Error> {
+        let mut errors = Vec::new();
+        let required = [
+            ("name", &self.name),
+            ("address", &self.address),
+            ("statement", &self.statement),
+            ("signature", &self.signature),
+        ];
+        for (field, value) in required {
+            if value.trim().is_empty() {
+                errors.push(FieldError::new(field, "REQUIRED"));
+            } else if value.chars().count() > MAX_FIELD_LENGTH {
+                errors.push(FieldError::new(field, "FIELD_TOO_LONG"));
+            }
+        }
+        let email = self.email.trim().to_string();
+        if !is_valid_email(&email) {
+            errors.push(FieldError::new("email", "INVALID_EMAIL"));
+        }
+        if !self.consent_to_jurisdiction {
+            errors.push(FieldError::new("consentToJurisdiction", "CONSENT_REQUIRED"));
+        }
+        if !errors.is_empty() {
+            return Err(Error::Validation(errors));
+        }
+        Ok(CreateCounterNotice {
+            name: self.name.trim().to_string(),
+            email,
+            address: self.address.trim().to_string(),
+            statement: self.statement.trim().to_string(),
+            consent_to_jurisdiction: true,
+            signature: self.signature.trim().to_string(),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListCounterNotices {
+    pub pending: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetLegalAction {
+    pub pending: bool,
+}
+
+/// A paste owner's objection to a takedown. Once `restore_after` passes the
+/// paste is reinstated, unless an operator has flagged pending legal action.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CounterNotice {
+    pub id: i64,
+    pub paste_id: String,
+    pub report_id: i64,
+    pub name: String,
+    pub email: String,
+    pub address: String,
+    pub statement: String,
+    pub signature: String,
+    pub created_at: i64,
+    pub restore_after: i64,
+    pub legal_action_pending: bool,
+    pub restored_at: Option<i64>,
+}
+
+struct CounterNoticeRow {
+    id: i64,
+    paste_id: String,
+    report_id: i64,
+    name: String,
+    email: String,
+    address: String,
+    statement: String,
+    signature: String,
+    created_at: i64,
+    restore_after: i64,
+    legal_action_pending: bool,
+    restored_at: Option<i64>,
+    key_id: Option<String>,
+}
+
+impl CounterNoticeRow {
+    fn decrypt(self, keyring: &Keyring) -> Result<CounterNotice, Error> {
+        let key_id = self.key_id.as_deref();
+        Ok(CounterNotice {
+            id: self.id,
+            paste_id: self.paste_id,
+            report_id: self.report_id,
+            name: keyring.open_text(key_id, NAME_CONTEXT, self.name)?,
+            email: keyring.open_text(key_id, EMAIL_CONTEXT, self.email)?,
+            address: keyring.open_text(key_id, ADDRESS_CONTEXT, self.address)?,
+            statement: keyring.open_text(key_id, STATEMENT_CONTEXT, self.statement)?,
+            signature: keyring.open_text(key_id, SIGNATURE_CONTEXT, self.signature)?,
+            created_at: self.created_at,
+            restore_after: self.restore_after,
+            legal_action_pending: self.legal_action_pending,
+            restored_at: self.restored_at,
+        })
+    }
+}
+
+impl CounterNotice {
+    /// Files a counter-notice against the report that took a paste down and
+    /// forwards it to the original reporter.
+    pub async fn create(
+        pool: &SqlitePool,
+        mailer: &Mailer,
+        settings: &ReportSettings,
+        paste_id: String,
+        payload: CreateCounterNotice,
+    ) -> Result<CounterNotice, Error> {
+        let payload = payload.validate()?;
+        let mut tx = pool.begin().await?;
+        let paste = sqlx::query!(
+            r#"SELECT status AS "status: PasteStatus", status_report_id
+                FROM paste WHERE id = ? AND deleted_at IS NULL"#,
+            paste_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let report_id = match (paste.status, paste.status_report_id) {
+            (PasteStatus::TakenDown, Some(report_id)) => report_id,
+            _ => return Err(Error::InvalidTransition),
+        };
+        // A notice filed against an earlier takedown does not block one
+        // against the current report.
+        let pending = sqlx::query_scalar!(
+            r#"SELECT EXISTS (
+                SELECT 1 FROM counter_notice
+                    WHERE paste_id = ? AND report_id = ? AND restored_at IS NULL
+            ) AS "exists!: bool""#,
+            paste_id,
+            report_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if pending {
+            return Err(Error::InvalidTransition);
+        }
+        let report = sqlx::query!(
+            "SELECT email, pii_key_id, links, locale FROM report WHERE id = ?",
+            report_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let now = now_millis();
+        let restore_after = now + settings.counter_notice_waiting_days * DAY_MILLIS;
+        let keyring = &settings.keyring;
+        let key_id = keyring.active_key_id();
+        let sealed_name = keyring.seal_text(NAME_CONTEXT, &payload.name);
+        let sealed_email = keyring.seal_text(EMAIL_CONTEXT, &payload.email);
+        let sealed_address = keyring.seal_text(ADDRESS_CONTEXT, &payload.address);
+        let sealed_statement = keyring.seal_text(STATEMENT_CONTEXT, &payload.statement);
+        let sealed_signature = keyring.seal_text(SIGNATURE_CONTEXT, &payload.signature);
+        let id = sqlx::query!(
+            "INSERT INTO counter_notice
+                ( paste_id, report_id, name, email, address, statement, signature, created_at, restore_after, key_id )
+                VALUES ( ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10 )",
+            paste_id,
+            report_id,
+            sealed_name,
+            sealed_email,
+            sealed_address,
+            sealed_statement,
+            sealed_signature,
+            now,
+            restore_after,
+            key_id
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+        // Counter-notices come from anonymous paste owners, so there is no
+        // one to name but the role.
+        let ctx = AuditContext {
+            actor: "paste_owner".to_string(),
+            reason: Some(format!("Counter-notice against report {}", report_id)),
+        };
+        AuditEntry::append(&mut tx, &ctx, "counter_notice.create", &id.to_string()).await?;
+        let notice = CounterNotice {
+            id,
+            paste_id,
+            report_id,
+            name: payload.name,
+            email: payload.email,
+            address: payload.address,
+            statement: payload.statement,
+            signature: payload.signature,
+            created_at: now,
+            restore_after,
+            legal_action_pending: false,
+            restored_at: None,
+        };
+
+        let email = keyring.open_text(
+            report.pii_key_id.as_deref(),
+            REPORT_EMAIL_CONTEXT,
+            report.email,
+        )?;
+        // The reporter's address is gone once the report has been anonymised.
+        if !email.is_empty() {
+            let links: Vec<String> = report.links.split('\n').map(|s| s.to_string()).collect();
+            mailer
+                .forward_counter_notice(&mut tx, &email, &report.locale, &links, &notice)
+                .await?;
+        }
+        tx.commit().await?;
+        mailer.notify();
+        Ok(notice)
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        query: ListCounterNotices,
+    ) -> Result<Vec<CounterNotice>, Error> {
+        let pending_only = query.pending.unwrap_or(false);
+        let rows = sqlx::query_as!(
+            CounterNoticeRow,
+            r#"SELECT id AS "id!",
+                      paste_id,
+                      report_id,
+                      name,
+                      email,
+                      address,
+                      statement,
+                      signature,
+                      created_at,
+                      restore_after,
+                      legal_action_pending,
+                      restored_at,
+                      key_id
+                FROM counter_notice
+                WHERE NOT ?1 OR restored_at IS NULL
+                ORDER BY id"#,
+            pending_only
+        )
+        .fetch_all(pool)
+        .await?;
+        rows.into_iter().map(|row| row.decrypt(keyring)).collect()
+    }
+
+    pub async fn for_report(
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        report_id: i64,
+    ) -> Result<Vec<CounterNotice>, Error> {
+        let rows = sqlx::query_as!(
+            CounterNoticeRow,
+            r#"SELECT id AS "id!",
+                      paste_id,
+                      report_id,
+                      name,
+                      email,
+                      address,
+                      statement,
+                      signature,
+                      created_at,
+                      restore_after,
+                      legal_action_pending,
+                      restored_at,
+                      key_id
+                FROM counter_notice WHERE report_id = ? ORDER BY id"#,
+            report_id
+        )
+        .fetch_all(pool)
+        .await?;
+        rows.into_iter().map(|row| row.decrypt(keyring)).collect()
+    }
+
+    /// Marks whether the original reporter has started legal action. While
+    /// it is pending the paste is never restored automatically.
+    pub async fn set_legal_action(
+        pool: &SqlitePool,
+        id: i64,
+        payload: SetLegalAction,
+        ctx: &AuditContext,
+    ) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let result = sqlx::query!(
+            "UPDATE counter_notice SET legal_action_pending = ?1
+                WHERE id = ?2 AND restored_at IS NULL",
+            payload.pending,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        let action = if payload.pending {
+            "counter_notice.legal_action"
+        } else {
+            "counter_notice.legal_action_cleared"
+        };
+        AuditEntry::append(&mut tx, ctx, action, &id.to_string()).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Reinstates every paste whose counter-notice waiting period ended
+    /// before `now`, as long as it is still down because of the same report.
+    pub async fn restore_due(pool: &SqlitePool, now: i64) -> Result<u64, Error> {
+        let mut tx = pool.begin().await?;
+        let due = sqlx::query!(
+            r#"SELECT counter_notice.id AS "id!", counter_notice.paste_id
+                FROM counter_notice
+                JOIN paste ON paste.id = counter_notice.paste_id
+                WHERE counter_notice.restored_at IS NULL
+                    AND NOT counter_notice.legal_action_pending
+                    AND counter_notice.restore_after <= ?1
+                    AND paste.status = ?2
+                    AND paste.status_report_id = counter_notice.report_id
+                    AND paste.deleted_at IS NULL"#,
+            now,
+            PasteStatus::TakenDown
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let ctx = AuditContext::system("counter-notice waiting period elapsed");
+        for notice in &due {
+            sqlx::query!(
+                "UPDATE paste
+                    SET
+                        status = ?1,
+                        status_reason = NULL,
+                        status_report_id = NULL
+                    WHERE id = ?2",
+                PasteStatus::Active,
+                notice.paste_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "UPDATE counter_notice SET restored_at = ?1 WHERE id = ?2",
+                now,
+                notice.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            AuditEntry::append(&mut tx, &ctx, "paste.reinstate", &notice.paste_id).await?;
+        }
+        tx.commit().await?;
+        Ok(due.len() as u64)
+    }
+
+    /// Re-encrypts up to `limit` counter-notices not sealed with the active
+    /// key. Returns the number rewritten.
+    pub(crate) async fn reencrypt_batch(
+        conn: &mut SqliteConnection,
+        keyring: &Keyring,
+        limit: i64,
+    ) -> Result<u64, Error> {
+        let key_id = keyring.active_key_id();
+        let notices = sqlx::query!(
+            r#"SELECT id AS "id!", name, email, address, statement, signature, key_id
+                FROM counter_notice WHERE key_id IS NOT ?1 LIMIT ?2"#,
+            key_id,
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        for notice in &notices {
+            let reseal = |context, value: &String| {
+                keyring
+                    .open_text(notice.key_id.as_deref(), context, value.clone())
+                    .map(|plaintext| keyring.seal_text(context, &plaintext))
+            };
+            let name = reseal(NAME_CONTEXT, &notice.name)?;
+            let email = reseal(EMAIL_CONTEXT, &notice.email)?;
+            let address = reseal(ADDRESS_CONTEXT, &notice.address)?;
+            let statement = reseal(STATEMENT_CONTEXT, &notice.statement)?;
+            let signature = reseal(SIGNATURE_CONTEXT, &notice.signature)?;
+            sqlx::query!(
+                "UPDATE counter_notice
+                    SET name = ?1, email = ?2, address = ?3, statement = ?4, signature = ?5, key_id = ?6
+                    WHERE id = ?7",
+                name,
+                email,
+                address,
+                statement,
+                signature,
+                key_id,
+                notice.id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+        Ok(notices.len() as u64)
+    }
+}
@@ -0,0 +1,148 @@
+use crate::error::Error;
+use crate::mailer::Mailer;
+use crate::models::report::{ReportCategory, ReportSettings, ReportStatus};
+use crate::models::{DAY_MILLIS, HOUR_MILLIS};
+use chrono::{NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// When the daily moderation digest goes out, and where it links to.
+#[derive(Clone, Debug)]
+pub struct DigestSettings {
+    /// Local time of day after which the digest is sent.
+    pub time: NaiveTime,
+    pub timezone: Tz,
+    /// A report in the admin interface, with `{id}` standing for its id.
+    pub report_url: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DigestReport {
+    pub id: i64,
+    pub ticket_id: String,
+    pub status: ReportStatus,
+    pub link: String,
+}
+
+/// The reports of one category that need an operator's attention. Each
+/// report is listed once, under the most pressing heading that applies.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct CategoryDigest {
+    pub category: ReportCategory,
+    /// Unresolved reports older than the category's response time.
+    pub overdue: Vec<DigestReport>,
+    /// Reports confirmed since the previous digest.
+    pub new: Vec<DigestReport>,
+    /// Any other unresolved reports.
+    pub open: Vec<DigestReport>,
+}
+
+pub struct ModerationDigest;
+
+impl ModerationDigest {
+    /// Sends the digest for the current local day once its time has come.
+    /// Each day's digest is recorded, so it goes out once however often
+    /// this runs and however many servers run it. Returns whether it was
+    /// sent.
+    pub async fn send_due(
+        pool: &SqlitePool,
+        mailer: &Mailer,
+        settings: &ReportSettings,
+        digest: &DigestSettings,
+        now: i64,
+    ) -> Result<bool, Error> {
+        let local = Utc
+            .timestamp_millis_opt(now)
+            .unwrap()
+            .with_timezone(&digest.timezone);
+        if local.time() < digest.time {
+            return Ok(false);
+        }
+        let date = local.date_naive().to_string();
+        let mut tx = pool.begin().await?;
+        let previous = sqlx::query_scalar!("SELECT MAX(sent_at) FROM moderation_digest")
+            .fetch_one(&mut *tx)
+            .await?;
+        let claimed = sqlx::query!(
+            "INSERT OR IGNORE INTO moderation_digest ( date, sent_at ) VALUES ( ?1, ?2 )",
+            date,
+            now
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            return Ok(false);
+        }
+        let since = previous.unwrap_or(now - DAY_MILLIS);
+        let categories = ModerationDigest::build(&mut tx, digest, since, now).await?;
+        mailer
+            .send_digest(&mut tx, &date, &categories, &settings.category_recipients)
+            .await?;
+        tx.commit().await?;
+        mailer.notify();
+        Ok(true)
+    }
+
+    /// Groups the reports confirmed since `since` and every unresolved one
+    /// by category, most urgent category first.
+    async fn build(
+        conn: &mut SqliteConnection,
+        digest: &DigestSettings,
+        since: i64,
+        now: i64,
+    ) -> Result<Vec<CategoryDigest>, Error> {
+        let reports = sqlx::query!(
+            r#"SELECT id AS "id!",
+                      ticket_id AS "ticket_id!",
+                      category AS "category: ReportCategory",
+                      status AS "status: ReportStatus",
+                      verified_at AS "verified_at!"
+                FROM report
+                WHERE deleted_at IS NULL
+                    AND verified_at IS NOT NULL
+                    AND (status IN (?1, ?2) OR verified_at >= ?3)
+                ORDER BY id"#,
+            ReportStatus::Open,
+            ReportStatus::InReview,
+            since
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut categories = ReportCategory::ALL;
+        categories.sort_by_key(|category| category.priority());
+        let mut sections = Vec::new();
+        for category in categories {
+            let mut section = CategoryDigest {
+                category,
+                overdue: Vec::new(),
+                new: Vec::new(),
+                open: Vec::new(),
+            };
+            for report in reports.iter().filter(|r| r.category == category) {
+                let unresolved =
+                    matches!(report.status, ReportStatus::Open | ReportStatus::InReview);
+                let due_at = report.verified_at + category.sla_hours() * HOUR_MILLIS;
+                let group = if unresolved && due_at < now {
+                    &mut section.overdue
+                } else if report.verified_at >= since {
+                    &mut section.new
+                } else {
+                    &mut section.open
+                };
+                group.push(DigestReport {
+                    id: report.id,
+                    ticket_id: report.ticket_id.clone(),
+                    status: report.status,
+                    link: digest.report_url.replace("{id}", &report.id.to_string()),
+                });
+            }
+            if !(section.overdue.is_empty() && section.new.is_empty() && section.open.is_empty()) {
+                sections.push(section);
+            }
+        }
+        Ok(sections)
+    }
+}
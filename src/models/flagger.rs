@@ -0,0 +1,246 @@
+use crate::crypto::Keyring;
+use crate::error::{Error, FieldError};
+use crate::models::audit::{AuditContext, AuditEntry};
+use crate::models::report::is_valid_email;
+use crate::models::{hash_token, now_millis, random_token};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+const MAX_NAME_LENGTH: usize = 200;
+const EMAIL_CONTEXT: &str = "trusted_flagger.email";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTrustedFlagger {
+    pub name: String,
+    /// Where confirmations and outcomes of the flagger's reports are sent.
+    pub email: String,
+    pub immediate_quarantine: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListTrustedFlaggers {
+    pub include_revoked: Option<bool>,
+}
+
+/// A partner, such as a rights-holder agent or a CERT, allowed to file
+/// reports over the API. Reports from flaggers with `immediate_quarantine`
+/// hide the pastes they point at before any operator has looked at them.
+#[derive(Serialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedFlagger {
+    pub id: i64,
+    pub name: String,
+    pub email: String,
+    pub immediate_quarantine: bool,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+}
+
+struct TrustedFlaggerRow {
+    id: i64,
+    name: String,
+    email: String,
+    immediate_quarantine: bool,
+    created_at: i64,
+    last_used_at: Option<i64>,
+    revoked_at: Option<i64>,
+    key_id: Option<String>,
+}
+
+impl TrustedFlaggerRow {
+    fn decrypt(self, keyring: &Keyring) -> Result<TrustedFlagger, Error> {
+        Ok(TrustedFlagger {
+            id: self.id,
+            name: self.name,
+            email: keyring.open_text(self.key_id.as_deref(), EMAIL_CONTEXT, self.email)?,
+            immediate_quarantine: self.immediate_quarantine,
+            created_at: self.created_at,
+            last_used_at: self.last_used_at,
+            revoked_at: self.revoked_at,
+        })
+    }
+}
+
+/// A newly issued flagger together with its API key. The key is only ever
+/// returned here; the database keeps its hash.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedTrustedFlagger {
+    #[serde(flatten)]
+    pub flagger: TrustedFlagger,
+    pub api_key: String,
+}
+
+impl TrustedFlagger {
+    /// The actor recorded in the audit log for actions taken on this
+    /// flagger's behalf.
+    pub fn actor(&self) -> String {
+        format!("flagger:{}", self.id)
+    }
+
+    pub async fn create(
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        payload: CreateTrustedFlagger,
+        ctx: &AuditContext,
+    ) -> Result<IssuedTrustedFlagger, Error> {
+        let name = payload.name.trim();
+        let email = payload.email.trim();
+        let mut errors = Vec::new();
+        if name.is_empty() {
+            errors.push(FieldError::new("name", "REQUIRED"));
+        } else if name.chars().count() > MAX_NAME_LENGTH {
+            errors.push(FieldError::new("name", "FIELD_TOO_LONG"));
+        }
+        if !is_valid_email(email) {
+            errors.push(FieldError::new("email", "INVALID_EMAIL"));
+        }
+        if !errors.is_empty() {
+            return Err(Error::Validation(errors));
+        }
+
+        let api_key = random_token();
+        let key_hash = hash_token(&api_key);
+        let immediate_quarantine = payload.immediate_quarantine.unwrap_or(false);
+        let created_at = now_millis();
+        let key_id = keyring.active_key_id();
+        let sealed_email = keyring.seal_text(EMAIL_CONTEXT, email);
+        let mut tx = pool.begin().await?;
+        let flagger = sqlx::query_as!(
+            TrustedFlaggerRow,
+            r#"INSERT INTO trusted_flagger ( name, email, key_hash, immediate_quarantine, created_at, key_id )
+                VALUES ( ?1, ?2, ?3, ?4, ?5, ?6 )
+                RETURNING id AS "id!",
+                          name,
+                          email,
+                          immediate_quarantine,
+                          created_at,
+                          last_used_at,
+                          revoked_at,
+                          key_id"#,
+            name,
+            sealed_email,
+            key_hash,
+            immediate_quarantine,
+            created_at,
+            key_id
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .decrypt(keyring)?;
+        AuditEntry::append(&mut tx, ctx, "flagger.issue", &flagger.id.to_string()).await?;
+        tx.commit().await?;
+        Ok(IssuedTrustedFlagger { flagger, api_key })
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        query: ListTrustedFlaggers,
+    ) -> Result<Vec<TrustedFlagger>, Error> {
+        let include_revoked = query.include_revoked.unwrap_or(false);
+        let rows = sqlx::query_as!(
+            TrustedFlaggerRow,
+            r#"SELECT id AS "id!",
+                      name,
+                      email,
+                      immediate_quarantine,
+                      created_at,
+                      last_used_at,
+                      revoked_at,
+                      key_id
+                FROM trusted_flagger
+                WHERE ?1 OR revoked_at IS NULL
+                ORDER BY id"#,
+            include_revoked
+        )
+        .fetch_all(pool)
+        .await?;
+        rows.into_iter().map(|row| row.decrypt(keyring)).collect()
+    }
+
+    /// Disables a key for good. Reports already filed with it are kept.
+    pub async fn revoke(pool: &SqlitePool, id: i64, ctx: &AuditContext) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let now = now_millis();
+        let result = sqlx::query!(
+            "UPDATE trusted_flagger SET revoked_at = ?1 WHERE id = ?2 AND revoked_at IS NULL",
+            now,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        AuditEntry::append(&mut tx, ctx, "flagger.revoke", &id.to_string()).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Resolves an API key to the flagger it was issued to.
+    pub async fn authenticate(
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        api_key: &str,
+    ) -> Result<TrustedFlagger, Error> {
+        let now = now_millis();
+        let key_hash = hash_token(api_key);
+        let row = sqlx::query_as!(
+            TrustedFlaggerRow,
+            r#"UPDATE trusted_flagger SET last_used_at = ?1
+                WHERE key_hash = ?2 AND revoked_at IS NULL
+                RETURNING id AS "id!",
+                          name,
+                          email,
+                          immediate_quarantine,
+                          created_at,
+                          last_used_at,
+                          revoked_at,
+                          key_id"#,
+            now,
+            key_hash
+        )
+        .fetch_optional(pool)
+        .await?;
+        row.ok_or(Error::Unauthorized)?.decrypt(keyring)
+    }
+
+    /// Re-encrypts up to `limit` flagger contacts not sealed with the active
+    /// key. Returns the number rewritten.
+    pub(crate) async fn reencrypt_batch(
+        conn: &mut SqliteConnection,
+        keyring: &Keyring,
+        limit: i64,
+    ) -> Result<u64, Error> {
+        let key_id = keyring.active_key_id();
+        let flaggers = sqlx::query!(
+            r#"SELECT id AS "id!", email, key_id
+                FROM trusted_flagger WHERE key_id IS NOT ?1 LIMIT ?2"#,
+            key_id,
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        for flagger in &flaggers {
+            let email = keyring.open_text(
+                flagger.key_id.as_deref(),
+                EMAIL_CONTEXT,
+                flagger.email.clone(),
+            )?;
+            let email = keyring.seal_text(EMAIL_CONTEXT, &email);
+            sqlx::query!(
+                "UPDATE trusted_flagger SET email = ?1, key_id = ?2 WHERE id = ?3",
+                email,
+                key_id,
+                flagger.id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+        Ok(flaggers.len() as u64)
+    }
+}
@@ -0,0 +1,153 @@
+use crate::error::Error;
+use crate::models::audit::{AuditContext, AuditEntry};
+use crate::models::now_millis;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum HoldTarget {
+    Paste,
+    Report,
+}
+
+impl HoldTarget {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HoldTarget::Paste => "paste",
+            HoldTarget::Report => "report",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateLegalHold {
+    pub target_type: HoldTarget,
+    pub target_id: String,
+    pub case_reference: String,
+    pub ends_at: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListLegalHolds {
+    pub include_inactive: Option<bool>,
+}
+
+/// A preservation order. While it is active the target is never physically
+/// deleted, although the public still sees it expire or disappear as usual.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LegalHold {
+    pub id: i64,
+    pub target_type: HoldTarget,
+    pub target_id: String,
+    pub case_reference: String,
+    pub created_at: i64,
+    pub ends_at: Option<i64>,
+    pub lifted_at: Option<i64>,
+}
+
+impl LegalHold {
+    pub async fn create(
+        pool: &SqlitePool,
+        payload: CreateLegalHold,
+        ctx: &AuditContext,
+    ) -> Result<LegalHold, Error> {
+        let mut tx = pool.begin().await?;
+        let exists = match payload.target_type {
+            HoldTarget::Paste => {
+                sqlx::query_scalar!(
+                    r#"SELECT EXISTS (SELECT 1 FROM paste WHERE id = ?) AS "exists!: bool""#,
+                    payload.target_id
+                )
+                .fetch_one(&mut *tx)
+                .await?
+            }
+            HoldTarget::Report => {
+                sqlx::query_scalar!(
+                    r#"SELECT EXISTS (SELECT 1 FROM report WHERE id = ?) AS "exists!: bool""#,
+                    payload.target_id
+                )
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+        if !exists {
+            return Err(Error::NotFound);
+        }
+
+        let created_at = now_millis();
+        let hold = sqlx::query_as!(
+            LegalHold,
+            r#"INSERT INTO legal_hold ( target_type, target_id, case_reference, created_at, ends_at )
+                VALUES ( ?1, ?2, ?3, ?4, ?5 )
+                RETURNING id AS "id!",
+                          target_type AS "target_type: HoldTarget",
+                          target_id,
+                          case_reference,
+                          created_at,
+                          ends_at,
+                          lifted_at"#,
+            payload.target_type,
+            payload.target_id,
+            payload.case_reference,
+            created_at,
+            payload.ends_at
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let ctx = AuditContext {
+            actor: ctx.actor.clone(),
+            reason: ctx.reason.clone().or(Some(hold.case_reference.clone())),
+        };
+        let action = format!("{}.hold", hold.target_type.as_str());
+        AuditEntry::append(&mut tx, &ctx, &action, &hold.target_id).await?;
+        tx.commit().await?;
+        Ok(hold)
+    }
+
+    pub async fn list(pool: &SqlitePool, query: ListLegalHolds) -> Result<Vec<LegalHold>, Error> {
+        let include_inactive = query.include_inactive.unwrap_or(false);
+        let now = now_millis();
+        let holds = sqlx::query_as!(
+            LegalHold,
+            r#"SELECT id AS "id!",
+                      target_type AS "target_type: HoldTarget",
+                      target_id,
+                      case_reference,
+                      created_at,
+                      ends_at,
+                      lifted_at
+                FROM legal_hold
+                WHERE ?1 OR (lifted_at IS NULL AND (ends_at IS NULL OR ends_at > ?2))
+                ORDER BY id"#,
+            include_inactive,
+            now
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(holds)
+    }
+
+    pub async fn lift(pool: &SqlitePool, id: i64, ctx: &AuditContext) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let now = now_millis();
+        let hold = sqlx::query!(
+            r#"UPDATE legal_hold SET lifted_at = ?1
+                WHERE id = ?2 AND lifted_at IS NULL
+                RETURNING target_type AS "target_type: HoldTarget", target_id"#,
+            now,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let action = format!("{}.release", hold.target_type.as_str());
+        AuditEntry::append(&mut tx, ctx, &action, &hold.target_id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
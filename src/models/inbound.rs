@@ -0,0 +1,165 @@
+use crate::crypto::Keyring;
+use crate::error::{Error, FieldError};
+use crate::mailer::Mailer;
+use crate::models::now_millis;
+use crate::models::report::{
+    is_valid_email, CreateReport, MessageDirection, Report, ReportCategory, ReportCorrespondence,
+    ReportSettings, MAX_MESSAGE_LENGTH,
+};
+use mail_parser::MessageParser;
+use sqlx::SqlitePool;
+use url::Url;
+
+/// An email received at the abuse address, reduced to what matters for
+/// moderation.
+#[derive(Debug, PartialEq)]
+pub struct InboundEmail {
+    pub from: String,
+    pub subject: String,
+    pub text: String,
+}
+
+impl InboundEmail {
+    /// Parses a raw RFC 822 message. Returns `None` when it has no usable
+    /// sender address.
+    pub fn from_rfc822(raw: &[u8]) -> Option<InboundEmail> {
+        let message = MessageParser::default().parse(raw)?;
+        let from = message.from()?.first()?.address()?.to_string();
+        Some(InboundEmail {
+            from,
+            subject: message.subject().unwrap_or_default().to_string(),
+            text: message
+                .body_text(0)
+                .map(|text| text.into_owned())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Builds an email from the fields of a SendGrid Inbound Parse POST,
+    /// where `from` may look like `Jane Doe <jane@example.com>`.
+    pub fn from_fields(from: &str, subject: &str, text: &str) -> InboundEmail {
+        let from = match (from.rfind('<'), from.rfind('>')) {
+            (Some(start), Some(end)) if start < end => &from[start + 1..end],
+            _ => from,
+        };
+        InboundEmail {
+            from: from.trim().to_string(),
+            subject: subject.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    /// Files the email as a new report, or appends it to an existing one
+    /// when it quotes a ticket id and comes from the original reporter.
+    pub async fn ingest(
+        self,
+        pool: &SqlitePool,
+        mailer: &Mailer,
+        settings: &ReportSettings,
+    ) -> Result<(), Error> {
+        if !is_valid_email(&self.from) {
+            return Err(Error::Validation(vec![FieldError::new(
+                "from",
+                "INVALID_EMAIL",
+            )]));
+        }
+
+        let ticket_id = find_ticket_id(&self.subject).or_else(|| find_ticket_id(&self.text));
+        if let Some(ticket_id) = ticket_id {
+            if self.append_to(pool, &settings.keyring, &ticket_id).await? {
+                return Ok(());
+            }
+        }
+
+        let links = find_links(&self.text, &settings.frontend_origin);
+        let message = self.text.chars().take(MAX_MESSAGE_LENGTH).collect();
+        Report::create(
+            pool,
+            mailer,
+            settings,
+            CreateReport {
+                links,
+                message,
+                email: self.from,
+                category: ReportCategory::Dmca,
+                attachments: Vec::new(),
+                locale: None,
+            },
+        )
+        .await
+    }
+
+    /// Stores the email as an inbound message on the report with this ticket
+    /// id. Returns `false` if no such report belongs to the sender.
+    async fn append_to(
+        &self,
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        ticket_id: &str,
+    ) -> Result<bool, Error> {
+        let mut tx = pool.begin().await?;
+        let now = now_millis();
+        let email_indexes = keyring.blind_indexes(&self.from);
+        let report_id = sqlx::query_scalar!(
+            r#"UPDATE report SET updated_at = ?1
+                WHERE ticket_id = ?2
+                    AND email_index IN (SELECT value FROM json_each(?3))
+                    AND deleted_at IS NULL
+                RETURNING id AS "id!""#,
+            now,
+            ticket_id,
+            email_indexes
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(report_id) = report_id else {
+            return Ok(false);
+        };
+        ReportCorrespondence::insert(
+            &mut tx,
+            keyring,
+            report_id,
+            MessageDirection::Inbound,
+            &self.from,
+            &self.subject,
+            &self.text,
+            now,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+}
+
+/// Finds the first ticket id of the form `AP-XXXX-XXXX-XXXX-XXXX-XXXX`.
+fn find_ticket_id(text: &str) -> Option<String> {
+    const GROUPS: usize = 5;
+    const LENGTH: usize = 3 + GROUPS * 5 - 1;
+    let bytes = text.as_bytes();
+    (0..bytes.len().saturating_sub(LENGTH - 1))
+        .map(|start| &bytes[start..start + LENGTH])
+        .find(|candidate| {
+            candidate.starts_with(b"AP-")
+                && candidate[3..].chunks(5).all(|group| {
+                    group[..4].iter().all(|c| c.is_ascii_hexdigit())
+                        && (group.len() == 4 || group[4] == b'-')
+                })
+        })
+        .map(|ticket_id| String::from_utf8_lossy(ticket_id).to_uppercase())
+}
+
+/// Collects every link in `text` that points at the frontend.
+fn find_links(text: &str, frontend_origin: &str) -> Vec<String> {
+    let Ok(origin) = Url::parse(frontend_origin).map(|url| url.origin()) else {
+        return Vec::new();
+    };
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| "<>()[]\"'.,;".contains(c)))
+        .filter(|word| {
+            Url::parse(word)
+                .map(|url| url.origin() == origin)
+                .unwrap_or(false)
+        })
+        .map(|link| link.to_string())
+        .collect()
+}
@@ -1,2 +1,50 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub mod attachment;
+pub mod audit;
+pub mod counter_notice;
+pub mod digest;
+pub mod flagger;
+pub mod hold;
+pub mod inbound;
+pub mod outbox;
 pub mod paste;
 pub mod report;
+pub mod webhook;
+
+/// A page of results together with the cursor to fetch the next one.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<i64>,
+}
+
+/// One day in milliseconds.
+pub const DAY_MILLIS: i64 = 24 * HOUR_MILLIS;
+
+/// One hour in milliseconds.
+pub const HOUR_MILLIS: i64 = 60 * 60 * 1000;
+
+/// A random, unguessable token suitable for links sent by email.
+pub fn random_token() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+/// The form in which secret tokens are stored, so a database leak does not
+/// leak working links.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Current time in milliseconds since the epoch, the unit used for every timestamp column.
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+        .try_into()
+        .unwrap()
+}
@@ -0,0 +1,300 @@
+use crate::crypto::Keyring;
+use crate::error::Error;
+use crate::mailer::templates::RenderedEmail;
+use crate::models::audit::{AuditContext, AuditEntry};
+use crate::models::{now_millis, HOUR_MILLIS};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+const RECIPIENT_CONTEXT: &str = "email_outbox.recipient";
+const SUBJECT_CONTEXT: &str = "email_outbox.subject";
+const CONTENT_CONTEXT: &str = "email_outbox.content";
+const HTML_CONTEXT: &str = "email_outbox.html";
+
+/// Failed deliveries after which an email or webhook is dead-lettered.
+pub const MAX_ATTEMPTS: i64 = 10;
+const RETRY_BASE_MILLIS: i64 = 30 * 1000;
+const RETRY_MAX_MILLIS: i64 = 6 * HOUR_MILLIS;
+/// How long a claimed email is hidden from other deliveries, in case the
+/// process dies halfway through sending it.
+pub(crate) const CLAIM_MILLIS: i64 = 5 * 60 * 1000;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    Pending,
+    Dead,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListOutbox {
+    pub status: Option<OutboxStatus>,
+}
+
+/// An email waiting to be delivered or given up on. Delivered emails are
+/// removed from the outbox.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub template: String,
+    pub recipient: String,
+    pub subject: String,
+    pub status: OutboxStatus,
+    pub attempts: i64,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+}
+
+/// An email claimed for delivery.
+pub struct QueuedEmail {
+    pub id: i64,
+    pub recipient: String,
+    pub subject: String,
+    pub content: String,
+    pub html: String,
+    pub attempts: i64,
+}
+
+/// Delay before retrying an email that has failed `attempts` times.
+pub(crate) fn retry_delay(attempts: i64) -> i64 {
+    let exponent = (attempts - 1).clamp(0, 20) as u32;
+    (RETRY_BASE_MILLIS << exponent).min(RETRY_MAX_MILLIS)
+}
+
+impl OutboxEntry {
+    /// Queues a rendered email. Called within the transaction that caused
+    /// it, so the email is sent if and only if the change is committed.
+    pub(crate) async fn enqueue(
+        conn: &mut SqliteConnection,
+        keyring: &Keyring,
+        template: &str,
+        recipient: &str,
+        email: &RenderedEmail,
+    ) -> Result<i64, Error> {
+        let now = now_millis();
+        let recipient = keyring.seal_text(RECIPIENT_CONTEXT, recipient);
+        let subject = keyring.seal_text(SUBJECT_CONTEXT, &email.subject);
+        let content = keyring.seal_text(CONTENT_CONTEXT, &email.text);
+        let html = keyring.seal_text(HTML_CONTEXT, &email.html);
+        let key_id = keyring.active_key_id();
+        let id = sqlx::query!(
+            "INSERT INTO email_outbox
+                ( template, recipient, subject, content, html, key_id, next_attempt_at, created_at, updated_at )
+                VALUES ( ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7, ?7 )",
+            template,
+            recipient,
+            subject,
+            content,
+            html,
+            key_id,
+            now
+        )
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    /// Claims up to `limit` pending emails that are due. With `only_new`,
+    /// emails that have already failed are left to the background worker.
+    pub(crate) async fn claim(
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        only_new: bool,
+        limit: i64,
+    ) -> Result<Vec<QueuedEmail>, Error> {
+        let now = now_millis();
+        let claimed_until = now + CLAIM_MILLIS;
+        let rows = sqlx::query!(
+            r#"UPDATE email_outbox SET next_attempt_at = ?1
+                WHERE id IN (
+                    SELECT id FROM email_outbox
+                        WHERE status = ?2 AND next_attempt_at <= ?3 AND (NOT ?4 OR attempts = 0)
+                        ORDER BY next_attempt_at
+                        LIMIT ?5
+                )
+                RETURNING id AS "id!", recipient, subject, content, html, key_id, attempts"#,
+            claimed_until,
+            OutboxStatus::Pending,
+            now,
+            only_new,
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                let key_id = Some(row.key_id.as_str());
+                Ok(QueuedEmail {
+                    id: row.id,
+                    recipient: keyring.open_text(key_id, RECIPIENT_CONTEXT, row.recipient)?,
+                    subject: keyring.open_text(key_id, SUBJECT_CONTEXT, row.subject)?,
+                    content: keyring.open_text(key_id, CONTENT_CONTEXT, row.content)?,
+                    html: keyring.open_text(key_id, HTML_CONTEXT, row.html)?,
+                    attempts: row.attempts,
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) async fn delivered(pool: &SqlitePool, id: i64) -> Result<(), Error> {
+        sqlx::query!("DELETE FROM email_outbox WHERE id = ?", id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Schedules the next attempt with exponential backoff, or dead-letters
+    /// the email once it has failed `MAX_ATTEMPTS` times.
+    pub(crate) async fn failed(
+        pool: &SqlitePool,
+        email: &QueuedEmail,
+        error: &str,
+    ) -> Result<(), Error> {
+        let now = now_millis();
+        let attempts = email.attempts + 1;
+        let status = if attempts >= MAX_ATTEMPTS {
+            OutboxStatus::Dead
+        } else {
+            OutboxStatus::Pending
+        };
+        let next_attempt_at = now + retry_delay(attempts);
+        sqlx::query!(
+            "UPDATE email_outbox
+                SET status = ?1, attempts = ?2, next_attempt_at = ?3, last_error = ?4, updated_at = ?5
+                WHERE id = ?6",
+            status,
+            attempts,
+            next_attempt_at,
+            error,
+            now,
+            email.id
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        query: ListOutbox,
+    ) -> Result<Vec<OutboxEntry>, Error> {
+        let rows = sqlx::query!(
+            r#"SELECT id AS "id!",
+                      template,
+                      recipient,
+                      subject,
+                      key_id,
+                      status AS "status: OutboxStatus",
+                      attempts,
+                      next_attempt_at,
+                      last_error,
+                      created_at
+                FROM email_outbox
+                WHERE ?1 IS NULL OR status = ?1
+                ORDER BY id"#,
+            query.status
+        )
+        .fetch_all(pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                let key_id = Some(row.key_id.as_str());
+                Ok(OutboxEntry {
+                    id: row.id,
+                    template: row.template,
+                    recipient: keyring.open_text(key_id, RECIPIENT_CONTEXT, row.recipient)?,
+                    subject: keyring.open_text(key_id, SUBJECT_CONTEXT, row.subject)?,
+                    status: row.status,
+                    attempts: row.attempts,
+                    next_attempt_at: row.next_attempt_at,
+                    last_error: row.last_error,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Puts an email back in line for immediate delivery with a fresh set of
+    /// attempts, typically after fixing whatever made it fail.
+    pub async fn retry(pool: &SqlitePool, id: i64, ctx: &AuditContext) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let now = now_millis();
+        let result = sqlx::query!(
+            "UPDATE email_outbox
+                SET status = ?1, attempts = 0, next_attempt_at = ?2, updated_at = ?2
+                WHERE id = ?3",
+            OutboxStatus::Pending,
+            now,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        AuditEntry::append(&mut tx, ctx, "outbox.retry", &id.to_string()).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Deletes dead-lettered emails last touched before `before`, since they
+    /// hold reporters' addresses and messages.
+    pub async fn purge_dead(pool: &SqlitePool, before: i64) -> Result<u64, Error> {
+        let purged = sqlx::query!(
+            "DELETE FROM email_outbox WHERE status = ?1 AND updated_at < ?2",
+            OutboxStatus::Dead,
+            before
+        )
+        .execute(pool)
+        .await?
+        .rows_affected();
+        Ok(purged)
+    }
+
+    pub(crate) async fn reencrypt_batch(
+        conn: &mut SqliteConnection,
+        keyring: &Keyring,
+        limit: i64,
+    ) -> Result<u64, Error> {
+        let key_id = keyring.active_key_id();
+        let emails = sqlx::query!(
+            r#"SELECT id AS "id!", recipient, subject, content, html, key_id
+                FROM email_outbox WHERE key_id IS NOT ?1 LIMIT ?2"#,
+            key_id,
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        for email in &emails {
+            let reseal = |context, value: &String| {
+                keyring
+                    .open_text(Some(&email.key_id), context, value.clone())
+                    .map(|plaintext| keyring.seal_text(context, &plaintext))
+            };
+            let recipient = reseal(RECIPIENT_CONTEXT, &email.recipient)?;
+            let subject = reseal(SUBJECT_CONTEXT, &email.subject)?;
+            let content = reseal(CONTENT_CONTEXT, &email.content)?;
+            let html = reseal(HTML_CONTEXT, &email.html)?;
+            sqlx::query!(
+                "UPDATE email_outbox
+                    SET recipient = ?1, subject = ?2, content = ?3, html = ?4, key_id = ?5
+                    WHERE id = ?6",
+                recipient,
+                subject,
+                content,
+                html,
+                key_id,
+                email.id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+        Ok(emails.len() as u64)
+    }
+}
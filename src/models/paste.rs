@@ -1,4 +1,7 @@
 use crate::error::Error;
+use crate::models::audit::{AuditContext, AuditEntry};
+use crate::models::{now_millis, Page};
+use crate::webhooks::{WebhookEvent, Webhooks};
 use serde::{Deserialize, Serialize};
 use sqlx::pool::PoolConnection;
 use sqlx::{Connection, Sqlite, SqlitePool};
@@ -29,16 +32,97 @@ pub struct Paste {
     pub expiry_views: Option<i64>,
 }
 
+/// Moderation state of a paste. Only active pastes are ever served; the
+/// others are retained for legal review.
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum PasteStatus {
+    Active,
+    Quarantined,
+    TakenDown,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePasteStatus {
+    pub status: PasteStatus,
+    pub reason: Option<String>,
+    pub report_id: Option<i64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ListPastes {
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+    pub created_after: Option<i64>,
+    pub created_before: Option<i64>,
+    pub min_size: Option<i64>,
+    pub max_size: Option<i64>,
+    pub expired: Option<bool>,
+    pub reported: Option<bool>,
+    pub status: Option<PasteStatus>,
+}
+
+/// Paste metadata as seen by moderators. `size` is the length of the
+/// encrypted content.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PasteSummary {
+    #[serde(skip)]
+    pub cursor: i64,
+    pub id: String,
+    pub created_at: i64,
+    pub size: i64,
+    pub expiry_time: Option<i64>,
+    pub expiry_views: Option<i64>,
+    pub pinned: bool,
+    pub expired: bool,
+    pub reported: bool,
+    pub status: PasteStatus,
+    pub status_reason: Option<String>,
+    pub status_report_id: Option<i64>,
+}
+
+/// A deleted paste waiting in the trash to be restored or purged.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedPaste {
+    pub id: String,
+    pub deleted_at: i64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PasteDetails {
+    pub id: String,
+    pub content: String,
+    pub created_at: i64,
+    pub size: i64,
+    pub expiry_time: Option<i64>,
+    pub expiry_views: Option<i64>,
+    pub pinned: bool,
+    pub expired: bool,
+    pub reported: bool,
+    pub status: PasteStatus,
+    pub status_reason: Option<String>,
+    pub status_report_id: Option<i64>,
+    pub deleted_at: Option<i64>,
+}
+
 impl Paste {
     pub async fn create(pool: &SqlitePool, payload: CreatePaste) -> Result<(), Error> {
         let mut conn = pool.acquire().await?;
+        let created_at = now_millis();
         sqlx::query!(
-            "INSERT INTO paste ( id, content, expiry_time, expiry_views )
-                VALUES ( ?1, ?2, ?3, ?4)",
+            "INSERT INTO paste ( id, content, expiry_time, expiry_views, created_at )
+                VALUES ( ?1, ?2, ?3, ?4, ?5 )",
             payload.id,
             payload.content,
             payload.expiry_time,
-            payload.expiry_views
+            payload.expiry_views,
+            created_at
         )
         .execute(&mut *conn)
         .await?;
@@ -47,22 +131,26 @@ impl Paste {
 
     pub async fn view(pool: &SqlitePool, id: String) -> Result<Self, Error> {
         let mut conn: PoolConnection<Sqlite> = pool.acquire().await?;
-        let paste = conn
+        let row = conn
             .transaction::<_, _, sqlx::error::Error>(|trans| {
                 Box::pin(async move {
-                    let paste = sqlx::query_as!(
-                        Paste,
-                        "SELECT id, 
+                    let row = sqlx::query!(
+                        r#"SELECT id, 
                                 content, 
                                 expiry_time, 
-                                expiry_views 
-                            FROM paste WHERE id = ?",
+                                expiry_views,
+                                pinned,
+                                status AS "status: PasteStatus",
+                                status_reason
+                            FROM paste WHERE id = ? AND deleted_at IS NULL"#,
                         id,
                     )
                     .fetch_one(&mut **trans)
                     .await?;
 
-                    if let Some(views) = paste.expiry_views {
+                    // Pinned pastes are exempt from expiry, so they don't burn views either.
+                    let burns_views = row.status == PasteStatus::Active && !row.pinned;
+                    if let (Some(views), true) = (row.expiry_views, burns_views) {
                         if views > 0_i64 {
                             sqlx::query!(
                                 "UPDATE paste
@@ -76,11 +164,29 @@ impl Paste {
                         }
                     }
 
-                    Ok(paste)
+                    Ok(row)
                 })
             })
             .await?;
 
+        match row.status {
+            PasteStatus::Active => (),
+            PasteStatus::Quarantined => return Err(Error::NotFound),
+            PasteStatus::TakenDown => {
+                return Err(Error::UnavailableForLegalReasons(row.status_reason))
+            }
+        }
+
+        let paste = Paste {
+            id: row.id,
+            content: row.content,
+            expiry_time: row.expiry_time,
+            expiry_views: row.expiry_views,
+        };
+        if row.pinned {
+            return Ok(paste);
+        }
+
         let time: i64 = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -101,30 +207,297 @@ impl Paste {
         Ok(paste)
     }
 
-    pub async fn update(pool: &SqlitePool, id: String, payload: UpdatePaste) -> Result<(), Error> {
-        let mut conn = pool.acquire().await?;
-        sqlx::query!(
+    pub async fn list(pool: &SqlitePool, query: ListPastes) -> Result<Page<PasteSummary>, Error> {
+        let now = now_millis();
+        let cursor = query.cursor.unwrap_or(0);
+        let limit = query.limit.unwrap_or(50).clamp(1, 500);
+        let items = sqlx::query_as!(
+            PasteSummary,
+            r#"SELECT cursor AS "cursor!: i64",
+                      id AS "id!",
+                      created_at AS "created_at!",
+                      size AS "size!: i64",
+                      expiry_time,
+                      expiry_views,
+                      pinned AS "pinned!: bool",
+                      expired AS "expired!: bool",
+                      reported AS "reported!: bool",
+                      status AS "status!: PasteStatus",
+                      status_reason,
+                      status_report_id
+                FROM (
+                    SELECT rowid AS cursor,
+                           id,
+                           created_at,
+                           length(content) AS size,
+                           expiry_time,
+                           expiry_views,
+                           pinned,
+                           NOT pinned AND (expiry_time < ?1 OR expiry_views = 0) IS TRUE AS expired,
+                           EXISTS (
+                               SELECT 1 FROM report_paste
+                               JOIN report ON report.id = report_paste.report_id
+                               WHERE report_paste.paste_id = paste.id
+                                   AND report.deleted_at IS NULL
+                                   AND report.verified_at IS NOT NULL
+                           ) AS reported,
+                           status,
+                           status_reason,
+                           status_report_id
+                        FROM paste
+                        WHERE deleted_at IS NULL
+                )
+                WHERE cursor > ?2
+                    AND (?3 IS NULL OR created_at >= ?3)
+                    AND (?4 IS NULL OR created_at < ?4)
+                    AND (?5 IS NULL OR size >= ?5)
+                    AND (?6 IS NULL OR size <= ?6)
+                    AND (?7 IS NULL OR expired = ?7)
+                    AND (?8 IS NULL OR reported = ?8)
+                    AND (?9 IS NULL OR status = ?9)
+                ORDER BY cursor
+                LIMIT ?10"#,
+            now,
+            cursor,
+            query.created_after,
+            query.created_before,
+            query.min_size,
+            query.max_size,
+            query.expired,
+            query.reported,
+            query.status,
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let next_cursor = match items.last() {
+            Some(last) if items.len() as i64 == limit => Some(last.cursor),
+            _ => None,
+        };
+        Ok(Page { items, next_cursor })
+    }
+
+    /// Reads a paste for moderation without consuming a view.
+    pub async fn inspect(pool: &SqlitePool, id: String) -> Result<PasteDetails, Error> {
+        let now = now_millis();
+        let paste = sqlx::query_as!(
+            PasteDetails,
+            r#"SELECT id,
+                      content,
+                      created_at,
+                      length(content) AS "size!: i64",
+                      expiry_time,
+                      expiry_views,
+                      pinned AS "pinned!: bool",
+                      (NOT pinned AND (expiry_time < ?1 OR expiry_views = 0) IS TRUE) AS "expired!: bool",
+                      EXISTS (
+                          SELECT 1 FROM report_paste
+                          JOIN report ON report.id = report_paste.report_id
+                          WHERE report_paste.paste_id = paste.id
+                              AND report.deleted_at IS NULL
+                              AND report.verified_at IS NOT NULL
+                      ) AS "reported!: bool",
+                      status AS "status: PasteStatus",
+                      status_reason,
+                      status_report_id,
+                      deleted_at
+                FROM paste WHERE id = ?2"#,
+            now,
+            id
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(paste)
+    }
+
+    pub async fn set_pinned(
+        pool: &SqlitePool,
+        id: String,
+        pinned: bool,
+        ctx: &AuditContext,
+    ) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let result = sqlx::query!(
+            "UPDATE paste SET pinned = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+            pinned,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        let action = if pinned { "paste.pin" } else { "paste.unpin" };
+        AuditEntry::append(&mut tx, ctx, action, &id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Moves a paste between active, quarantined and taken down. Nothing is
+    /// deleted, so the content stays available for legal review.
+    pub async fn set_status(
+        pool: &SqlitePool,
+        webhooks: &Webhooks,
+        id: String,
+        payload: UpdatePasteStatus,
+        ctx: &AuditContext,
+    ) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let result = sqlx::query!(
+            "UPDATE paste
+                SET
+                    status = ?1,
+                    status_reason = ?2,
+                    status_report_id = ?3
+                WHERE id = ?4 AND deleted_at IS NULL",
+            payload.status,
+            payload.reason,
+            payload.report_id,
+            id,
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        let action = match payload.status {
+            PasteStatus::Active => "paste.reinstate",
+            PasteStatus::Quarantined => "paste.quarantine",
+            PasteStatus::TakenDown => "paste.take_down",
+        };
+        AuditEntry::append(&mut tx, ctx, action, &id).await?;
+        if payload.status == PasteStatus::TakenDown {
+            let event = WebhookEvent::PasteTakenDown {
+                id,
+                reason: payload.reason,
+                report_id: payload.report_id,
+            };
+            webhooks.emit(&mut tx, event).await?;
+        }
+        tx.commit().await?;
+        webhooks.notify();
+        Ok(())
+    }
+
+    pub async fn update(
+        pool: &SqlitePool,
+        id: String,
+        payload: UpdatePaste,
+        ctx: &AuditContext,
+    ) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        // Held pastes are evidence, so their content must stay as it was.
+        let held = sqlx::query_scalar!(
+            r#"SELECT EXISTS (
+                SELECT 1 FROM active_legal_hold WHERE target_type = 'paste' AND target_id = ?1
+            ) AS "held!: bool""#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if held {
+            return Err(Error::UnderLegalHold);
+        }
+        let result = sqlx::query!(
             "UPDATE paste
                 SET
                     content = ?1,
                     expiry_time = ?2,
                     expiry_views = ?3
-                WHERE id = ?4",
+                WHERE id = ?4 AND deleted_at IS NULL",
             payload.content,
             payload.expiry_time,
             payload.expiry_views,
             id,
         )
-        .execute(&mut *conn)
+        .execute(&mut *tx)
         .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        AuditEntry::append(&mut tx, ctx, "paste.update", &id).await?;
+        tx.commit().await?;
         Ok(())
     }
 
-    pub async fn delete(pool: &SqlitePool, id: String) -> Result<(), Error> {
-        let mut conn = pool.acquire().await?;
-        sqlx::query!("DELETE FROM paste WHERE id = ?", id)
-            .execute(&mut *conn)
+    /// Moves a paste to the trash. It disappears from public reads straight
+    /// away and is purged for real once the trash retention has passed.
+    pub async fn delete(
+        pool: &SqlitePool,
+        webhooks: &Webhooks,
+        id: String,
+        ctx: &AuditContext,
+    ) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let now = now_millis();
+        let result = sqlx::query!(
+            "UPDATE paste SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+            now,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        AuditEntry::append(&mut tx, ctx, "paste.delete", &id).await?;
+        webhooks
+            .emit(&mut tx, WebhookEvent::PasteDeleted { id })
             .await?;
+        tx.commit().await?;
+        webhooks.notify();
+        Ok(())
+    }
+
+    pub async fn trashed(pool: &SqlitePool) -> Result<Vec<TrashedPaste>, Error> {
+        let pastes = sqlx::query_as!(
+            TrashedPaste,
+            r#"SELECT id, deleted_at AS "deleted_at!"
+                FROM paste WHERE deleted_at IS NOT NULL ORDER BY deleted_at"#
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(pastes)
+    }
+
+    pub async fn restore(pool: &SqlitePool, id: String, ctx: &AuditContext) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let result = sqlx::query!(
+            "UPDATE paste SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        AuditEntry::append(&mut tx, ctx, "paste.restore", &id).await?;
+        tx.commit().await?;
         Ok(())
     }
+
+    /// Physically deletes pastes that have been in the trash since before
+    /// `deleted_before`. Pastes under legal hold stay in the trash.
+    pub async fn purge_trash(pool: &SqlitePool, deleted_before: i64) -> Result<u64, Error> {
+        let mut tx = pool.begin().await?;
+        let ids = sqlx::query_scalar!(
+            "DELETE FROM paste
+                WHERE deleted_at < ?
+                    AND NOT EXISTS (
+                        SELECT 1 FROM active_legal_hold
+                        WHERE target_type = 'paste' AND target_id = paste.id
+                    )
+                RETURNING id",
+            deleted_before
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let ctx = AuditContext::system("trash retention elapsed");
+        for id in &ids {
+            AuditEntry::append(&mut tx, &ctx, "paste.purge", id).await?;
+        }
+        tx.commit().await?;
+        Ok(ids.len() as u64)
+    }
 }
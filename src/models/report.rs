@@ -1,7 +1,30 @@
-use crate::error::Error;
-use crate::mailer::Mailer;
+use crate::crypto::Keyring;
+use crate::error::{Error, FieldError};
+use crate::mailer::{templates::DEFAULT_LOCALE, Mailer, Outcome};
+use crate::models::attachment::{
+    validate_attachments, CreateAttachment, ReportAttachment, ValidAttachment,
+};
+use crate::models::audit::{AuditContext, AuditEntry};
+use crate::models::counter_notice::CounterNotice;
+use crate::models::flagger::TrustedFlagger;
+use crate::models::outbox::OutboxEntry;
+use crate::models::paste::PasteStatus;
+use crate::models::{hash_token, now_millis, random_token, Page, HOUR_MILLIS};
+use crate::webhooks::{WebhookEvent, Webhooks};
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+use url::Url;
+
+const MAX_LINKS: usize = 50;
+pub(crate) const MAX_MESSAGE_LENGTH: usize = 10_000;
+const MAX_EMAIL_LENGTH: usize = 254;
+pub(crate) const EMAIL_CONTEXT: &str = "report.email";
+const MESSAGE_CONTEXT: &str = "report.message";
+const AUTHOR_CONTEXT: &str = "report_message.author";
+const SUBJECT_CONTEXT: &str = "report_message.subject";
+const BODY_CONTEXT: &str = "report_message.body";
+const NOTE_CONTEXT: &str = "report_note.body";
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -9,77 +32,1617 @@ pub struct CreateReport {
     pub links: Vec<String>,
     pub message: String,
     pub email: String,
+    #[serde(default)]
+    pub category: ReportCategory,
+    #[serde(default)]
+    pub attachments: Vec<CreateAttachment>,
+    /// The language to write to the reporter in, as a tag such as `de` or
+    /// `pt-BR`. The `Accept-Language` header is used when it is missing.
+    #[serde(default)]
+    pub locale: Option<String>,
+}
+/// A report filed over the API by a trusted flagger. The reporter email is
+/// the one the flagger was registered with.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateFlaggedReport {
+    pub links: Vec<String>,
+    pub message: String,
+    #[serde(default)]
+    pub category: ReportCategory,
+    #[serde(default)]
+    pub attachments: Vec<CreateAttachment>,
+}
+
+/// What a trusted flagger gets back for a report: its ticket id and the
+/// pastes it quarantined straight away.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FlaggedReport {
+    pub id: i64,
+    pub ticket_id: String,
+    pub quarantined: Vec<String>,
 }
+
+/// A report that passed validation: a well-formed email, a deduplicated
+/// list of links to pastes on our own frontend and decoded attachments.
+struct ValidReport {
+    links: Vec<String>,
+    paste_ids: Vec<String>,
+    message: String,
+    email: String,
+    category: ReportCategory,
+    attachments: Vec<ValidAttachment>,
+    locale: String,
+}
+
+impl CreateReport {
+    /// Checks the report, which will be written to the reporter in `locale`,
+    /// the supported locale closest to the one they asked for.
+    fn validate(self, settings: &ReportSettings, locale: String) -> Result<ValidReport, Error> {
+        let mut errors = Vec::new();
+
+        let email = self.email.trim().to_string();
+        if !is_valid_email(&email) {
+            errors.push(FieldError::new("email", "INVALID_EMAIL"));
+        }
+
+        if self.message.chars().count() > MAX_MESSAGE_LENGTH {
+            errors.push(FieldError::new("message", "MESSAGE_TOO_LONG"));
+        }
+
+        let mut links = Vec::new();
+        let mut paste_ids = Vec::new();
+        if self.links.is_empty() {
+            errors.push(FieldError::new("links", "NO_LINKS"));
+        } else if self.links.len() > MAX_LINKS {
+            errors.push(FieldError::new("links", "TOO_MANY_LINKS"));
+        } else {
+            let origin = Url::parse(&settings.frontend_origin)
+                .map(|url| url.origin())
+                .ok();
+            for (i, link) in self.links.iter().enumerate() {
+                let field = format!("links[{}]", i);
+                let url = match Url::parse(link.trim()) {
+                    Ok(url) => url,
+                    Err(_) => {
+                        errors.push(FieldError::new(&field, "INVALID_LINK"));
+                        continue;
+                    }
+                };
+                if origin.as_ref() != Some(&url.origin()) {
+                    errors.push(FieldError::new(&field, "FOREIGN_LINK"));
+                    continue;
+                }
+                let paste_id = url
+                    .path_segments()
+                    .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+                    .map(|id| id.to_string());
+                let Some(paste_id) = paste_id else {
+                    errors.push(FieldError::new(&field, "INVALID_LINK"));
+                    continue;
+                };
+                let link = url.to_string();
+                if links.contains(&link) {
+                    continue;
+                }
+                links.push(link);
+                if !paste_ids.contains(&paste_id) {
+                    paste_ids.push(paste_id);
+                }
+            }
+        }
+
+        let attachments = validate_attachments(self.attachments, &mut errors);
+
+        if !errors.is_empty() {
+            return Err(Error::Validation(errors));
+        }
+        Ok(ValidReport {
+            links,
+            paste_ids,
+            message: self.message,
+            email,
+            category: self.category,
+            attachments,
+            locale,
+        })
+    }
+}
+
+/// A reporter-facing reference such as `AP-1A2B-3C4D-5E6F-7A8B-9C0D`, with
+/// 80 random bits so it cannot be guessed.
+fn new_ticket_id() -> String {
+    let hex = hex::encode_upper(rand::random::<[u8; 10]>());
+    let groups: Vec<&str> = (0..hex.len()).step_by(4).map(|i| &hex[i..i + 4]).collect();
+    format!("AP-{}", groups.join("-"))
+}
+
+/// A deliberately conservative syntax check: one `@`, a non-empty local part
+/// and a dotted domain, with no whitespace anywhere.
+pub(crate) fn is_valid_email(email: &str) -> bool {
+    if email.len() > MAX_EMAIL_LENGTH || email.chars().any(|c| c.is_whitespace()) {
+        return false;
+    }
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.split('.').count() >= 2
+                && domain.split('.').all(|label| {
+                    !label.is_empty()
+                        && !label.starts_with('-')
+                        && !label.ends_with('-')
+                        && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+                })
+        }
+        None => false,
+    }
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct ReportRow {
+    pub id: i64,
     pub links: String,
     pub message: String,
     pub email: String,
+    pub pii_key_id: Option<String>,
+    pub category: ReportCategory,
+    pub priority: i64,
+    pub status: ReportStatus,
+    pub created_at: i64,
 }
 #[derive(Serialize, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Report {
+    pub id: i64,
     pub links: Vec<String>,
     pub message: String,
     pub email: String,
+    pub category: ReportCategory,
+    pub priority: i64,
+    pub status: ReportStatus,
+    pub created_at: i64,
 }
 
-impl Into<Report> for ReportRow {
-    fn into(self) -> Report {
-        Report {
-            links: self.links.split("\n").map(|s| s.to_string()).collect(),
-            message: self.message,
-            email: self.email,
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportSort {
+    #[default]
+    Newest,
+    Oldest,
+    /// Most urgent first, oldest first within the same priority.
+    Priority,
+}
+
+impl ReportSort {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReportSort::Newest => "newest",
+            ReportSort::Oldest => "oldest",
+            ReportSort::Priority => "priority",
         }
     }
 }
 
+/// Spacing between priorities in the cursor of a priority-sorted listing,
+/// which packs `(priority, id)` into one ordered `i64`.
+const PRIORITY_CURSOR_STRIDE: i64 = 1 << 44;
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ListReports {
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+    pub status: Option<ReportStatus>,
+    pub created_after: Option<i64>,
+    pub created_before: Option<i64>,
+    pub email: Option<String>,
+    pub category: Option<ReportCategory>,
+    #[serde(default)]
+    pub sort: ReportSort,
+}
+
+/// What kind of abuse a report is about. Decides who is notified, how the
+/// emails read and how urgently the report is handled.
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ReportCategory {
+    #[default]
+    Dmca,
+    Csam,
+    Malware,
+    Phishing,
+    Harassment,
+}
+
+impl ReportCategory {
+    pub const ALL: [ReportCategory; 5] = [
+        ReportCategory::Dmca,
+        ReportCategory::Csam,
+        ReportCategory::Malware,
+        ReportCategory::Phishing,
+        ReportCategory::Harassment,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportCategory::Dmca => "dmca",
+            ReportCategory::Csam => "csam",
+            ReportCategory::Malware => "malware",
+            ReportCategory::Phishing => "phishing",
+            ReportCategory::Harassment => "harassment",
+        }
+    }
+
+    /// Queue priority. Lower is more urgent.
+    pub fn priority(&self) -> i64 {
+        match self {
+            ReportCategory::Csam => 0,
+            ReportCategory::Malware | ReportCategory::Phishing => 1,
+            ReportCategory::Harassment => 2,
+            ReportCategory::Dmca => 3,
+        }
+    }
+
+    /// Hours an operator has to review a report, as promised to the
+    /// reporter. Unresolved reports older than that are overdue.
+    pub fn sla_hours(&self) -> i64 {
+        match self {
+            ReportCategory::Csam => 1,
+            ReportCategory::Malware | ReportCategory::Phishing => 4,
+            ReportCategory::Harassment => 12,
+            ReportCategory::Dmca => 24,
+        }
+    }
+}
+
+/// Where a report is in the moderation workflow.
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ReportStatus {
+    Open,
+    InReview,
+    Actioned,
+    Rejected,
+    Closed,
+}
+
+impl ReportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportStatus::Open => "open",
+            ReportStatus::InReview => "in_review",
+            ReportStatus::Actioned => "actioned",
+            ReportStatus::Rejected => "rejected",
+            ReportStatus::Closed => "closed",
+        }
+    }
+
+    pub fn can_transition_to(&self, next: ReportStatus) -> bool {
+        use ReportStatus::*;
+        matches!(
+            (self, next),
+            (Open, InReview)
+                | (Open, Rejected)
+                | (Open, Closed)
+                | (InReview, Open)
+                | (InReview, Actioned)
+                | (InReview, Rejected)
+                | (Actioned, Closed)
+                | (Rejected, Open)
+                | (Rejected, Closed)
+                | (Closed, Open)
+        )
+    }
+
+    /// The outcome communicated to the reporter when a case reaches this status.
+    fn outcome(&self) -> Option<Outcome> {
+        match self {
+            ReportStatus::Actioned => Some(Outcome::Actioned),
+            ReportStatus::Rejected => Some(Outcome::Rejected),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransitionReport {
+    pub status: ReportStatus,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignReport {
+    pub assignee: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateReportNote {
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateReportReply {
+    pub body: String,
+}
+
+/// Whether a message on a report was sent to the reporter or received from them.
+#[derive(Serialize, Deserialize, sqlx::Type, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum MessageDirection {
+    Outbound,
+    Inbound,
+}
+
+/// One email in the conversation between operators and the reporter.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportCorrespondence {
+    pub id: i64,
+    pub direction: MessageDirection,
+    pub author: String,
+    pub subject: String,
+    pub body: String,
+    pub created_at: i64,
+}
+
+/// An internal operator note. Never shown to the reporter.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportNote {
+    pub id: i64,
+    pub author: String,
+    pub body: String,
+    pub created_at: i64,
+}
+
+/// A single report with its case state, as seen by operators.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportCase {
+    pub id: i64,
+    pub ticket_id: String,
+    pub links: Vec<String>,
+    pub message: String,
+    pub email: String,
+    pub category: ReportCategory,
+    pub priority: i64,
+    pub status: ReportStatus,
+    pub assignee: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub resolved_at: Option<i64>,
+    pub trusted_flagger_id: Option<i64>,
+    pub notes: Vec<ReportNote>,
+    pub messages: Vec<ReportCorrespondence>,
+    pub attachments: Vec<ReportAttachment>,
+    pub counter_notices: Vec<CounterNotice>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LookupTicket {
+    pub ticket_id: String,
+    pub email: String,
+}
+
+/// What a reporter can see about their own report. Deliberately leaves out
+/// the message, assignee and operator notes.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportTicket {
+    pub ticket_id: String,
+    pub links: Vec<String>,
+    pub category: ReportCategory,
+    pub verified: bool,
+    pub status: ReportStatus,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub resolved_at: Option<i64>,
+}
+
+/// Server-side settings that shape how incoming reports are handled.
+#[derive(Clone, Debug)]
+pub struct ReportSettings {
+    /// Keys that encrypt reporters' personal data at rest.
+    pub keyring: Keyring,
+    /// Origin of the frontend. Only links to pastes on it can be reported.
+    pub frontend_origin: String,
+    /// Hours a reporter has to confirm their email before the report expires.
+    pub verification_ttl_hours: i64,
+    /// Number of distinct reports after which a paste is quarantined
+    /// pending review. `None` disables auto-hiding.
+    pub auto_hide_threshold: Option<i64>,
+    /// Operators notified of new reports in each category. Categories
+    /// without recipients go to the default sender address.
+    pub category_recipients: HashMap<ReportCategory, Vec<String>>,
+    /// Whether operators get an email for every confirmed report, rather
+    /// than only the daily digest.
+    pub operator_report_emails: bool,
+    /// Days a counter-notice must stand unanswered before the paste it
+    /// concerns is restored.
+    pub counter_notice_waiting_days: i64,
+    /// Days a report stays closed before the reporter's personal data is
+    /// anonymised.
+    pub pii_retention_days: i64,
+}
+
+/// All live reports filed against a single paste.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PasteReports {
+    pub paste_id: String,
+    pub report_count: i64,
+    pub report_ids: Vec<i64>,
+}
+
+/// A closed report whose personal data is due to be anonymised.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionCandidate {
+    pub id: i64,
+    pub ticket_id: String,
+    pub category: ReportCategory,
+    pub closed_at: i64,
+}
+
+/// A deleted report waiting in the trash to be restored or purged.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashedReport {
+    pub id: i64,
+    pub deleted_at: i64,
+}
+
+impl ReportRow {
+    fn decrypt(self, keyring: &Keyring) -> Result<Report, Error> {
+        let key_id = self.pii_key_id.as_deref();
+        Ok(Report {
+            id: self.id,
+            links: self.links.split("\n").map(|s| s.to_string()).collect(),
+            message: keyring.open_text(key_id, MESSAGE_CONTEXT, self.message)?,
+            email: keyring.open_text(key_id, EMAIL_CONTEXT, self.email)?,
+            category: self.category,
+            priority: self.priority,
+            status: self.status,
+            created_at: self.created_at,
+        })
+    }
+}
+
+impl ReportCorrespondence {
+    /// Stores a message on a report, encrypting everything the reporter
+    /// might have written.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn insert(
+        conn: &mut SqliteConnection,
+        keyring: &Keyring,
+        report_id: i64,
+        direction: MessageDirection,
+        author: &str,
+        subject: &str,
+        body: &str,
+        created_at: i64,
+    ) -> Result<ReportCorrespondence, Error> {
+        let key_id = keyring.active_key_id();
+        let sealed_author = keyring.seal_text(AUTHOR_CONTEXT, author);
+        let sealed_subject = keyring.seal_text(SUBJECT_CONTEXT, subject);
+        let sealed_body = keyring.seal_text(BODY_CONTEXT, body);
+        let id = sqlx::query!(
+            "INSERT INTO report_message ( report_id, direction, author, subject, body, created_at, key_id )
+                VALUES ( ?1, ?2, ?3, ?4, ?5, ?6, ?7 )",
+            report_id,
+            direction,
+            sealed_author,
+            sealed_subject,
+            sealed_body,
+            created_at,
+            key_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid();
+        Ok(ReportCorrespondence {
+            id,
+            direction,
+            author: author.to_string(),
+            subject: subject.to_string(),
+            body: body.to_string(),
+            created_at,
+        })
+    }
+
+    async fn for_report(
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        report_id: i64,
+    ) -> Result<Vec<ReportCorrespondence>, Error> {
+        let rows = sqlx::query!(
+            r#"SELECT id AS "id!",
+                      direction AS "direction: MessageDirection",
+                      author,
+                      subject,
+                      body,
+                      created_at,
+                      key_id
+                FROM report_message WHERE report_id = ? ORDER BY id"#,
+            report_id
+        )
+        .fetch_all(pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                let key_id = row.key_id.as_deref();
+                Ok(ReportCorrespondence {
+                    id: row.id,
+                    direction: row.direction,
+                    author: keyring.open_text(key_id, AUTHOR_CONTEXT, row.author)?,
+                    subject: keyring.open_text(key_id, SUBJECT_CONTEXT, row.subject)?,
+                    body: keyring.open_text(key_id, BODY_CONTEXT, row.body)?,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+}
+
 impl Report {
-    pub async fn list(pool: &SqlitePool) -> Result<Vec<Report>, Error> {
-        let reports = sqlx::query_as!(ReportRow, "SELECT links, message, email FROM report",)
-            .fetch_all(pool)
-            .await
-            .map(|result| result.into_iter().map(|row| row.into()).collect())?;
-        Ok(reports)
+    pub async fn list(
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        query: ListReports,
+    ) -> Result<Page<Report>, Error> {
+        let limit = query.limit.unwrap_or(50).clamp(1, 500);
+        let sort = query.sort.as_str();
+        let email_indexes = query.email.as_deref().map(|email| keyring.blind_indexes(email));
+        // Rows stored before encryption have no blind index until `reencrypt`
+        // has run, so those are matched on the plaintext address.
+        let email = query.email.map(|email| email.trim().to_lowercase());
+        let rows = sqlx::query_as!(
+            ReportRow,
+            r#"SELECT id AS "id!",
+                      links,
+                      message,
+                      email,
+                      pii_key_id,
+                      category AS "category: ReportCategory",
+                      priority,
+                      status AS "status: ReportStatus",
+                      created_at
+                FROM report
+                WHERE deleted_at IS NULL
+                    AND verified_at IS NOT NULL
+                    AND (?1 IS NULL OR (CASE ?2
+                        WHEN 'newest' THEN id < ?1
+                        WHEN 'oldest' THEN id > ?1
+                        ELSE priority * ?8 + id > ?1
+                    END))
+                    AND (?3 IS NULL OR status = ?3)
+                    AND (?4 IS NULL OR created_at >= ?4)
+                    AND (?5 IS NULL OR created_at < ?5)
+                    AND (?6 IS NULL
+                        OR email_index IN (SELECT value FROM json_each(?6))
+                        OR (pii_key_id IS NULL AND lower(trim(email)) = ?10))
+                    AND (?9 IS NULL OR category = ?9)
+                ORDER BY CASE ?2
+                    WHEN 'newest' THEN -id
+                    WHEN 'oldest' THEN id
+                    ELSE priority * ?8 + id
+                END
+                LIMIT ?7"#,
+            query.cursor,
+            sort,
+            query.status,
+            query.created_after,
+            query.created_before,
+            email_indexes,
+            limit,
+            PRIORITY_CURSOR_STRIDE,
+            query.category,
+            email
+        )
+        .fetch_all(pool)
+        .await?;
+        let items = rows
+            .into_iter()
+            .map(|row| row.decrypt(keyring))
+            .collect::<Result<Vec<Report>, Error>>()?;
+
+        let next_cursor = match items.last() {
+            Some(last) if items.len() as i64 == limit => match query.sort {
+                ReportSort::Priority => Some(last.priority * PRIORITY_CURSOR_STRIDE + last.id),
+                ReportSort::Newest | ReportSort::Oldest => Some(last.id),
+            },
+            _ => None,
+        };
+        Ok(Page { items, next_cursor })
     }
 
     pub async fn create(
         pool: &SqlitePool,
         mailer: &Mailer,
+        settings: &ReportSettings,
         payload: CreateReport,
     ) -> Result<(), Error> {
-        let mut conn = pool.acquire().await?;
-        let links_txt = payload.links.join("\n");
-        sqlx::query!(
-            "INSERT INTO report ( links, message, email ) VALUES ( ?1, ?2, ?3 )",
+        let locale = mailer.negotiate_locale(payload.locale.as_deref());
+        let report = payload.validate(settings, locale)?;
+        let mut tx = pool.begin().await?;
+        let token = random_token();
+        let token_hash = hash_token(&token);
+        let (_, ticket_id) =
+            Report::insert(&mut tx, &settings.keyring, &report, Some(&token_hash), None).await?;
+        let confirmation_link = format!(
+            "{}/report/verify/{}",
+            settings.frontend_origin.trim_end_matches('/'),
+            token
+        );
+        mailer
+            .confirm_report(
+                &mut tx,
+                &report.email,
+                &report.locale,
+                &ticket_id,
+                &report.links,
+                report.category,
+                &confirmation_link,
+            )
+            .await?;
+        tx.commit().await?;
+        mailer.notify();
+
+        Ok(())
+    }
+
+    /// Files a report from a trusted flagger. It skips email confirmation and,
+    /// if the flagger is allowed to, quarantines every active paste it
+    /// points at until an operator reviews the case.
+    pub async fn create_flagged(
+        pool: &SqlitePool,
+        mailer: &Mailer,
+        webhooks: &Webhooks,
+        settings: &ReportSettings,
+        flagger: &TrustedFlagger,
+        payload: CreateFlaggedReport,
+    ) -> Result<FlaggedReport, Error> {
+        let report = CreateReport {
+            links: payload.links,
+            message: payload.message,
+            email: flagger.email.clone(),
+            category: payload.category,
+            attachments: payload.attachments,
+            locale: None,
+        }
+        .validate(settings, DEFAULT_LOCALE.to_string())?;
+        let mut tx = pool.begin().await?;
+        let (id, ticket_id) =
+            Report::insert(&mut tx, &settings.keyring, &report, None, Some(flagger.id)).await?;
+        let ctx = AuditContext {
+            actor: flagger.actor(),
+            reason: None,
+        };
+        AuditEntry::append(&mut tx, &ctx, "report.create", &id.to_string()).await?;
+
+        let mut quarantined = Vec::new();
+        if flagger.immediate_quarantine {
+            let reason = format!(
+                "Quarantined on report from trusted flagger {}",
+                flagger.name
+            );
+            let ctx = AuditContext {
+                actor: flagger.actor(),
+                reason: Some(reason.clone()),
+            };
+            for paste_id in &report.paste_ids {
+                let hidden = sqlx::query!(
+                    "UPDATE paste
+                        SET
+                            status = ?1,
+                            status_reason = ?2,
+                            status_report_id = ?3
+                        WHERE id = ?4 AND status = ?5 AND deleted_at IS NULL",
+                    PasteStatus::Quarantined,
+                    reason,
+                    id,
+                    paste_id,
+                    PasteStatus::Active
+                )
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+                if hidden > 0 {
+                    AuditEntry::append(&mut tx, &ctx, "paste.quarantine", paste_id).await?;
+                    quarantined.push(paste_id.clone());
+                }
+            }
+        }
+        let operators = settings
+            .category_recipients
+            .get(&report.category)
+            .map(|recipients| recipients.as_slice())
+            .unwrap_or_default();
+        mailer
+            .respond_to(
+                &mut tx,
+                &report.email,
+                &report.locale,
+                &report.links,
+                report.category,
+            )
+            .await?;
+        if settings.operator_report_emails {
+            mailer
+                .forward_report(
+                    &mut tx,
+                    &report.email,
+                    &report.links,
+                    report.category,
+                    operators,
+                )
+                .await?;
+        }
+        let event = WebhookEvent::ReportCreated {
+            id,
+            ticket_id: ticket_id.clone(),
+            category: report.category,
+            priority: report.category.priority(),
+            paste_ids: report.paste_ids.clone(),
+        };
+        webhooks.emit(&mut tx, event).await?;
+        tx.commit().await?;
+        mailer.notify();
+        webhooks.notify();
+
+        Ok(FlaggedReport {
+            id,
+            ticket_id,
+            quarantined,
+        })
+    }
+
+    /// Stores a validated report with the pastes it points at and its
+    /// attachments. Without a verification token the report counts as
+    /// confirmed straight away. Returns the new id and ticket id.
+    async fn insert(
+        conn: &mut SqliteConnection,
+        keyring: &Keyring,
+        report: &ValidReport,
+        verification_token: Option<&str>,
+        trusted_flagger_id: Option<i64>,
+    ) -> Result<(i64, String), Error> {
+        let links_txt = report.links.join("\n");
+        let now = now_millis();
+        let verified_at = match verification_token {
+            Some(_) => None,
+            None => Some(now),
+        };
+        let ticket_id = new_ticket_id();
+        let category = report.category;
+        let priority = category.priority();
+        let message = keyring.seal_text(MESSAGE_CONTEXT, &report.message);
+        let email = keyring.seal_text(EMAIL_CONTEXT, &report.email);
+        let email_index = keyring.blind_index(&report.email);
+        let key_id = keyring.active_key_id();
+        let report_id = sqlx::query!(
+            "INSERT INTO report ( links, message, email, email_index, pii_key_id, created_at, updated_at, verification_token, verified_at, ticket_id, category, priority, trusted_flagger_id, locale )
+                VALUES ( ?1, ?2, ?3, ?11, ?12, ?4, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?13 )",
             links_txt,
-            payload.message,
-            payload.email
+            message,
+            email,
+            now,
+            verification_token,
+            verified_at,
+            ticket_id,
+            category,
+            priority,
+            trusted_flagger_id,
+            email_index,
+            key_id,
+            report.locale
+        )
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid();
+
+        for paste_id in &report.paste_ids {
+            sqlx::query!(
+                "INSERT OR IGNORE INTO report_paste ( report_id, paste_id ) VALUES ( ?1, ?2 )",
+                report_id,
+                paste_id
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+        ReportAttachment::insert(conn, keyring, report_id, &report.attachments, now).await?;
+        Ok((report_id, ticket_id))
+    }
+
+    /// Confirms the reporter's email address. Only now does the report reach
+    /// the moderation queue, count towards auto-hiding and notify operators.
+    pub async fn verify(
+        pool: &SqlitePool,
+        mailer: &Mailer,
+        webhooks: &Webhooks,
+        settings: &ReportSettings,
+        token: String,
+    ) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let now = now_millis();
+        let created_after = now - settings.verification_ttl_hours * HOUR_MILLIS;
+        let token_hash = hash_token(&token);
+        let report = sqlx::query!(
+            r#"UPDATE report
+                SET verified_at = ?1, updated_at = ?1, verification_token = NULL
+                WHERE verification_token = ?2
+                    AND verified_at IS NULL
+                    AND deleted_at IS NULL
+                    AND created_at >= ?3
+                RETURNING id AS "id!",
+                          ticket_id AS "ticket_id!",
+                          links,
+                          email,
+                          pii_key_id,
+                          category AS "category: ReportCategory",
+                          priority,
+                          locale"#,
+            now,
+            token_hash,
+            created_after
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let paste_ids = sqlx::query_scalar!(
+            "SELECT paste_id FROM report_paste WHERE report_id = ?",
+            report.id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        if let Some(threshold) = settings.auto_hide_threshold {
+            for paste_id in &paste_ids {
+                Report::auto_hide(&mut tx, report.id, paste_id, threshold).await?;
+            }
+        }
+
+        let links: Vec<String> = report.links.split('\n').map(|s| s.to_string()).collect();
+        let email = settings.keyring.open_text(
+            report.pii_key_id.as_deref(),
+            EMAIL_CONTEXT,
+            report.email,
+        )?;
+        let operators = settings
+            .category_recipients
+            .get(&report.category)
+            .map(|recipients| recipients.as_slice())
+            .unwrap_or_default();
+        mailer
+            .respond_to(&mut tx, &email, &report.locale, &links, report.category)
+            .await?;
+        if settings.operator_report_emails {
+            mailer
+                .forward_report(&mut tx, &email, &links, report.category, operators)
+                .await?;
+        }
+        let event = WebhookEvent::ReportCreated {
+            id: report.id,
+            ticket_id: report.ticket_id,
+            category: report.category,
+            priority: report.priority,
+            paste_ids,
+        };
+        webhooks.emit(&mut tx, event).await?;
+        tx.commit().await?;
+        mailer.notify();
+        webhooks.notify();
+
+        Ok(())
+    }
+
+    /// Physically deletes reports whose email was never confirmed and that
+    /// were submitted before `created_before`.
+    pub async fn expire_unverified(pool: &SqlitePool, created_before: i64) -> Result<u64, Error> {
+        let mut tx = pool.begin().await?;
+        let ids = sqlx::query_scalar!(
+            r#"DELETE FROM report
+                WHERE verified_at IS NULL
+                    AND created_at < ?
+                    AND NOT EXISTS (
+                        SELECT 1 FROM active_legal_hold
+                        WHERE target_type = 'report' AND target_id = CAST(report.id AS TEXT)
+                    )
+                RETURNING id AS "id!""#,
+            created_before
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let ctx = AuditContext::system("email not confirmed in time");
+        for id in &ids {
+            AuditEntry::append(&mut tx, &ctx, "report.expire", &id.to_string()).await?;
+        }
+        tx.commit().await?;
+        Ok(ids.len() as u64)
+    }
+
+    /// Quarantines an active paste once enough distinct reports point at it.
+    async fn auto_hide(
+        conn: &mut SqliteConnection,
+        report_id: i64,
+        paste_id: &str,
+        threshold: i64,
+    ) -> Result<(), Error> {
+        let report_count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!: i64" FROM report_paste
+                JOIN report ON report.id = report_paste.report_id
+                WHERE report_paste.paste_id = ?
+                    AND report.deleted_at IS NULL
+                    AND report.verified_at IS NOT NULL"#,
+            paste_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        if report_count < threshold {
+            return Ok(());
+        }
+
+        let reason = format!("Automatically hidden after {} reports", report_count);
+        let hidden = sqlx::query!(
+            "UPDATE paste
+                SET
+                    status = ?1,
+                    status_reason = ?2,
+                    status_report_id = ?3
+                WHERE id = ?4 AND status = ?5 AND deleted_at IS NULL",
+            PasteStatus::Quarantined,
+            reason,
+            report_id,
+            paste_id,
+            PasteStatus::Active
         )
         .execute(&mut *conn)
+        .await?
+        .rows_affected();
+        if hidden > 0 {
+            let ctx = AuditContext::system(&reason);
+            AuditEntry::append(conn, &ctx, "paste.quarantine", paste_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Groups live reports by the paste they point at, most reported first.
+    pub async fn by_paste(pool: &SqlitePool) -> Result<Vec<PasteReports>, Error> {
+        let rows = sqlx::query!(
+            r#"SELECT report_paste.paste_id,
+                      COUNT(*) AS "report_count!: i64",
+                      group_concat(report_paste.report_id) AS "report_ids!: String"
+                FROM report_paste
+                JOIN report ON report.id = report_paste.report_id
+                WHERE report.deleted_at IS NULL AND report.verified_at IS NOT NULL
+                GROUP BY report_paste.paste_id
+                ORDER BY 2 DESC, report_paste.paste_id"#
+        )
+        .fetch_all(pool)
         .await?;
 
-        let (_link_ids, links): (Vec<String>, Vec<String>) = payload
-            .links
+        Ok(rows
             .into_iter()
-            .filter_map(|link| {
-                link.split(|c| c == '/' || c == '#')
-                    .nth(4)
-                    .map(|id| (String::from(id), link.clone()))
+            .map(|row| PasteReports {
+                paste_id: row.paste_id,
+                report_count: row.report_count,
+                report_ids: row
+                    .report_ids
+                    .split(',')
+                    .filter_map(|id| id.parse().ok())
+                    .collect(),
+            })
+            .collect())
+    }
+
+    /// Looks up a report for its reporter. Both the ticket id and the email
+    /// must match, and a mismatch is indistinguishable from a missing ticket.
+    /// Reports stored before encryption are matched on the plaintext email.
+    pub async fn lookup(
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        payload: LookupTicket,
+    ) -> Result<ReportTicket, Error> {
+        let ticket_id = payload.ticket_id.trim().to_uppercase();
+        let email_indexes = keyring.blind_indexes(&payload.email);
+        let email = payload.email.trim().to_lowercase();
+        let row = sqlx::query!(
+            r#"SELECT ticket_id AS "ticket_id!",
+                      links,
+                      category AS "category: ReportCategory",
+                      verified_at IS NOT NULL AS "verified!: bool",
+                      status AS "status: ReportStatus",
+                      created_at,
+                      updated_at,
+                      resolved_at
+                FROM report
+                WHERE ticket_id = ?1
+                    AND (email_index IN (SELECT value FROM json_each(?2))
+                        OR (pii_key_id IS NULL AND lower(trim(email)) = ?3))
+                    AND deleted_at IS NULL
+                    AND anonymised_at IS NULL"#,
+            ticket_id,
+            email_indexes,
+            email
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(ReportTicket {
+            ticket_id: row.ticket_id,
+            links: row.links.split('\n').map(|s| s.to_string()).collect(),
+            category: row.category,
+            verified: row.verified,
+            status: row.status,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            resolved_at: row.resolved_at,
+        })
+    }
+
+    pub async fn get(pool: &SqlitePool, keyring: &Keyring, id: i64) -> Result<ReportCase, Error> {
+        let row = sqlx::query!(
+            r#"SELECT id AS "id!",
+                      ticket_id AS "ticket_id!",
+                      links,
+                      message,
+                      email,
+                      pii_key_id,
+                      category AS "category: ReportCategory",
+                      priority,
+                      status AS "status: ReportStatus",
+                      assignee,
+                      created_at,
+                      updated_at,
+                      resolved_at,
+                      trusted_flagger_id
+                FROM report WHERE id = ? AND deleted_at IS NULL AND verified_at IS NOT NULL"#,
+            id
+        )
+        .fetch_one(pool)
+        .await?;
+        let notes = sqlx::query!(
+            r#"SELECT id AS "id!", author, body, created_at, key_id
+                FROM report_note WHERE report_id = ? ORDER BY id"#,
+            id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|note| {
+            Ok(ReportNote {
+                id: note.id,
+                author: note.author,
+                body: keyring.open_text(note.key_id.as_deref(), NOTE_CONTEXT, note.body)?,
+                created_at: note.created_at,
             })
-            .unzip();
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+        let messages = ReportCorrespondence::for_report(pool, keyring, id).await?;
+        let attachments = ReportAttachment::for_report(pool, id).await?;
+        let counter_notices = CounterNotice::for_report(pool, keyring, id).await?;
+        let key_id = row.pii_key_id.as_deref();
+
+        Ok(ReportCase {
+            id: row.id,
+            ticket_id: row.ticket_id,
+            links: row.links.split('\n').map(|s| s.to_string()).collect(),
+            message: keyring.open_text(key_id, MESSAGE_CONTEXT, row.message)?,
+            email: keyring.open_text(key_id, EMAIL_CONTEXT, row.email)?,
+            category: row.category,
+            priority: row.priority,
+            status: row.status,
+            assignee: row.assignee,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            resolved_at: row.resolved_at,
+            trusted_flagger_id: row.trusted_flagger_id,
+            notes,
+            messages,
+            attachments,
+            counter_notices,
+        })
+    }
+
+    /// Moves a case to a new status, rejecting transitions the workflow does
+    /// not allow. Resolving a case emails the reporter its outcome.
+    pub async fn transition(
+        pool: &SqlitePool,
+        mailer: &Mailer,
+        keyring: &Keyring,
+        id: i64,
+        payload: TransitionReport,
+        ctx: &AuditContext,
+    ) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let report = sqlx::query!(
+            r#"SELECT status AS "status: ReportStatus",
+                      category AS "category: ReportCategory",
+                      links,
+                      email,
+                      pii_key_id,
+                      resolved_at,
+                      locale
+                FROM report WHERE id = ? AND deleted_at IS NULL AND verified_at IS NOT NULL"#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if !report.status.can_transition_to(payload.status) {
+            return Err(Error::InvalidTransition);
+        }
+
+        let now = now_millis();
+        let resolved_at = match payload.status {
+            ReportStatus::Actioned | ReportStatus::Rejected => Some(now),
+            ReportStatus::Closed => report.resolved_at.or(Some(now)),
+            ReportStatus::Open | ReportStatus::InReview => None,
+        };
+        // Retention counts from the latest closing, so reopening a case
+        // restarts the clock.
+        let closed_at = (payload.status == ReportStatus::Closed).then_some(now);
+        sqlx::query!(
+            "UPDATE report
+                SET status = ?1, updated_at = ?2, resolved_at = ?3, closed_at = ?4
+                WHERE id = ?5",
+            payload.status,
+            now,
+            resolved_at,
+            closed_at,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        let action = format!("report.{}", payload.status.as_str());
+        AuditEntry::append(&mut tx, ctx, &action, &id.to_string()).await?;
+        if payload.status == ReportStatus::Rejected {
+            // Quarantine is only ever pending review; a rejected report
+            // hands its pastes back.
+            let reinstated = sqlx::query_scalar!(
+                "UPDATE paste
+                    SET
+                        status = ?1,
+                        status_reason = NULL,
+                        status_report_id = NULL
+                    WHERE status_report_id = ?2 AND status = ?3
+                    RETURNING id",
+                PasteStatus::Active,
+                id,
+                PasteStatus::Quarantined
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            for paste_id in &reinstated {
+                AuditEntry::append(&mut tx, ctx, "paste.reinstate", paste_id).await?;
+            }
+        }
+        if let Some(outcome) = payload.status.outcome() {
+            let links: Vec<String> = report.links.split('\n').map(|s| s.to_string()).collect();
+            let email =
+                keyring.open_text(report.pii_key_id.as_deref(), EMAIL_CONTEXT, report.email)?;
+            mailer
+                .notify_outcome(
+                    &mut tx,
+                    &email,
+                    &report.locale,
+                    &links,
+                    report.category,
+                    outcome,
+                )
+                .await?;
+        }
+        tx.commit().await?;
+        mailer.notify();
+        Ok(())
+    }
+
+    pub async fn assign(
+        pool: &SqlitePool,
+        id: i64,
+        payload: AssignReport,
+        ctx: &AuditContext,
+    ) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let now = now_millis();
+        let result = sqlx::query!(
+            "UPDATE report SET assignee = ?1, updated_at = ?2
+                WHERE id = ?3 AND deleted_at IS NULL AND verified_at IS NOT NULL",
+            payload.assignee,
+            now,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        AuditEntry::append(&mut tx, ctx, "report.assign", &id.to_string()).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn add_note(
+        pool: &SqlitePool,
+        keyring: &Keyring,
+        id: i64,
+        payload: CreateReportNote,
+        ctx: &AuditContext,
+    ) -> Result<ReportNote, Error> {
+        let mut tx = pool.begin().await?;
+        let now = now_millis();
+        let result = sqlx::query!(
+            "UPDATE report SET updated_at = ?1
+                WHERE id = ?2 AND deleted_at IS NULL AND verified_at IS NOT NULL",
+            now,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        let body = keyring.seal_text(NOTE_CONTEXT, &payload.body);
+        let key_id = keyring.active_key_id();
+        let note_id = sqlx::query!(
+            "INSERT INTO report_note ( report_id, author, body, created_at, key_id )
+                VALUES ( ?1, ?2, ?3, ?4, ?5 )",
+            id,
+            ctx.actor,
+            body,
+            now,
+            key_id
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+        let note = ReportNote {
+            id: note_id,
+            author: ctx.actor.clone(),
+            body: payload.body,
+            created_at: now,
+        };
+        AuditEntry::append(&mut tx, ctx, "report.note", &id.to_string()).await?;
+        tx.commit().await?;
+        Ok(note)
+    }
+
+    /// Emails the reporter on behalf of an operator and keeps a copy on the
+    /// report. The ticket id in the subject keeps the thread together.
+    pub async fn reply(
+        pool: &SqlitePool,
+        mailer: &Mailer,
+        keyring: &Keyring,
+        id: i64,
+        payload: CreateReportReply,
+        ctx: &AuditContext,
+    ) -> Result<ReportCorrespondence, Error> {
+        if payload.body.trim().is_empty() {
+            return Err(Error::Validation(vec![FieldError::new("body", "REQUIRED")]));
+        }
+        let mut tx = pool.begin().await?;
+        let now = now_millis();
+        let report = sqlx::query!(
+            r#"UPDATE report SET updated_at = ?1
+                WHERE id = ?2 AND deleted_at IS NULL AND verified_at IS NOT NULL
+                RETURNING ticket_id AS "ticket_id!",
+                          email,
+                          pii_key_id,
+                          category AS "category: ReportCategory",
+                          locale"#,
+            now,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let subject = mailer.reply_subject(&report.locale, report.category, &report.ticket_id);
+        let message = ReportCorrespondence::insert(
+            &mut tx,
+            keyring,
+            id,
+            MessageDirection::Outbound,
+            &ctx.actor,
+            &subject,
+            &payload.body,
+            now,
+        )
+        .await?;
+        AuditEntry::append(&mut tx, ctx, "report.reply", &id.to_string()).await?;
+        let email = keyring.open_text(report.pii_key_id.as_deref(), EMAIL_CONTEXT, report.email)?;
+        mailer
+            .reply_to_reporter(
+                &mut tx,
+                &email,
+                &report.locale,
+                &message.subject,
+                &message.body,
+            )
+            .await?;
+        tx.commit().await?;
+        mailer.notify();
+        Ok(message)
+    }
+
+    /// Moves a report to the trash until it is restored or purged.
+    pub async fn delete(pool: &SqlitePool, id: String, ctx: &AuditContext) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let now = now_millis();
+        let result = sqlx::query!(
+            "UPDATE report SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+            now,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        AuditEntry::append(&mut tx, ctx, "report.delete", &id).await?;
+        tx.commit().await?;
+        Ok(())
+    }
 
-        mailer.respond_to(&payload.email, &links).await?;
+    pub async fn trashed(pool: &SqlitePool) -> Result<Vec<TrashedReport>, Error> {
+        let reports = sqlx::query_as!(
+            TrashedReport,
+            r#"SELECT id AS "id!", deleted_at AS "deleted_at!"
+                FROM report WHERE deleted_at IS NOT NULL ORDER BY deleted_at"#
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(reports)
+    }
 
+    pub async fn restore(pool: &SqlitePool, id: String, ctx: &AuditContext) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let result = sqlx::query!(
+            "UPDATE report SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        AuditEntry::append(&mut tx, ctx, "report.restore", &id).await?;
+        tx.commit().await?;
         Ok(())
     }
 
-    pub async fn delete(pool: &SqlitePool, id: String) -> Result<(), Error> {
+    /// Physically deletes reports that have been in the trash since before
+    /// `deleted_before`. Reports under legal hold stay in the trash.
+    pub async fn purge_trash(pool: &SqlitePool, deleted_before: i64) -> Result<u64, Error> {
+        let mut tx = pool.begin().await?;
+        let ids = sqlx::query_scalar!(
+            r#"DELETE FROM report
+                WHERE deleted_at < ?
+                    AND NOT EXISTS (
+                        SELECT 1 FROM active_legal_hold
+                        WHERE target_type = 'report' AND target_id = CAST(report.id AS TEXT)
+                    )
+                RETURNING id AS "id!""#,
+            deleted_before
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let ctx = AuditContext::system("trash retention elapsed");
+        for id in &ids {
+            AuditEntry::append(&mut tx, &ctx, "report.purge", &id.to_string()).await?;
+        }
+        tx.commit().await?;
+        Ok(ids.len() as u64)
+    }
+
+    /// Lists the closed reports resolved before `closed_before` whose
+    /// personal data has not been anonymised yet. Reports under legal hold
+    /// are left alone.
+    pub async fn retention_due(
+        pool: &SqlitePool,
+        closed_before: i64,
+    ) -> Result<Vec<RetentionCandidate>, Error> {
         let mut conn = pool.acquire().await?;
-        sqlx::query!("DELETE FROM report WHERE id = ?", id)
-            .execute(&mut *conn)
+        Report::retention_candidates(&mut conn, closed_before).await
+    }
+
+    async fn retention_candidates(
+        conn: &mut SqliteConnection,
+        closed_before: i64,
+    ) -> Result<Vec<RetentionCandidate>, Error> {
+        let candidates = sqlx::query_as!(
+            RetentionCandidate,
+            r#"SELECT id AS "id!",
+                      ticket_id AS "ticket_id!",
+                      category AS "category: ReportCategory",
+                      closed_at AS "closed_at!"
+                FROM report
+                WHERE status = ?1
+                    AND closed_at < ?2
+                    AND anonymised_at IS NULL
+                    AND NOT EXISTS (
+                        SELECT 1 FROM active_legal_hold
+                        WHERE target_type = 'report' AND target_id = CAST(report.id AS TEXT)
+                    )
+                ORDER BY id"#,
+            ReportStatus::Closed,
+            closed_before
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(candidates)
+    }
+
+    /// Erases the reporter's email, message, correspondence and attachments
+    /// from every report returned by `retention_due`, along with whatever
+    /// paste owners wrote in counter-notices against it. Links, category,
+    /// status and timestamps are kept for statistics.
+    pub async fn anonymise_closed(pool: &SqlitePool, closed_before: i64) -> Result<u64, Error> {
+        let mut tx = pool.begin().await?;
+        let candidates = Report::retention_candidates(&mut tx, closed_before).await?;
+        let now = now_millis();
+        let ctx = AuditContext::system("report retention period elapsed");
+        for candidate in &candidates {
+            sqlx::query!(
+                "UPDATE report
+                    SET email = '', email_index = NULL, message = '', anonymised_at = ?1
+                    WHERE id = ?2",
+                now,
+                candidate.id
+            )
+            .execute(&mut *tx)
             .await?;
-        Ok(())
+            sqlx::query!(
+                "UPDATE report_message
+                    SET
+                        author = CASE direction WHEN ?1 THEN '' ELSE author END,
+                        subject = '',
+                        body = ''
+                    WHERE report_id = ?2",
+                MessageDirection::Inbound,
+                candidate.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "DELETE FROM report_attachment WHERE report_id = ?",
+                candidate.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "UPDATE counter_notice
+                    SET name = '', email = '', address = '', statement = '', signature = ''
+                    WHERE report_id = ?",
+                candidate.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            AuditEntry::append(&mut tx, &ctx, "report.anonymise", &candidate.id.to_string())
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(candidates.len() as u64)
+    }
+
+    /// Re-encrypts personal data written with a retired key, or before
+    /// encryption was introduced, with the active key. Returns the number of
+    /// reports, messages, notes, attachments, counter-notices, flaggers and
+    /// queued emails rewritten.
+    pub async fn reencrypt(pool: &SqlitePool, keyring: &Keyring) -> Result<u64, Error> {
+        const BATCH_SIZE: i64 = 100;
+        let key_id = keyring.active_key_id();
+        let mut rewritten = 0;
+        loop {
+            let mut tx = pool.begin().await?;
+            let reports = sqlx::query!(
+                r#"SELECT id AS "id!", email, message, pii_key_id
+                    FROM report WHERE pii_key_id IS NOT ?1 LIMIT ?2"#,
+                key_id,
+                BATCH_SIZE
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            for report in &reports {
+                let old_key_id = report.pii_key_id.as_deref();
+                let email = keyring.open_text(old_key_id, EMAIL_CONTEXT, report.email.clone())?;
+                let message =
+                    keyring.open_text(old_key_id, MESSAGE_CONTEXT, report.message.clone())?;
+                let email_index = match email.as_str() {
+                    "" => None,
+                    email => Some(keyring.blind_index(email)),
+                };
+                let email = keyring.seal_text(EMAIL_CONTEXT, &email);
+                let message = keyring.seal_text(MESSAGE_CONTEXT, &message);
+                sqlx::query!(
+                    "UPDATE report
+                        SET email = ?1, email_index = ?2, message = ?3, pii_key_id = ?4
+                        WHERE id = ?5",
+                    email,
+                    email_index,
+                    message,
+                    key_id,
+                    report.id
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            let messages = sqlx::query!(
+                r#"SELECT id AS "id!", author, subject, body, key_id
+                    FROM report_message WHERE key_id IS NOT ?1 LIMIT ?2"#,
+                key_id,
+                BATCH_SIZE
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            for message in &messages {
+                let old_key_id = message.key_id.as_deref();
+                let reseal = |context, value: &String| {
+                    keyring
+                        .open_text(old_key_id, context, value.clone())
+                        .map(|plaintext| keyring.seal_text(context, &plaintext))
+                };
+                let author = reseal(AUTHOR_CONTEXT, &message.author)?;
+                let subject = reseal(SUBJECT_CONTEXT, &message.subject)?;
+                let body = reseal(BODY_CONTEXT, &message.body)?;
+                sqlx::query!(
+                    "UPDATE report_message
+                        SET author = ?1, subject = ?2, body = ?3, key_id = ?4
+                        WHERE id = ?5",
+                    author,
+                    subject,
+                    body,
+                    key_id,
+                    message.id
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            let notes = sqlx::query!(
+                r#"SELECT id AS "id!", body, key_id
+                    FROM report_note WHERE key_id IS NOT ?1 LIMIT ?2"#,
+                key_id,
+                BATCH_SIZE
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            for note in &notes {
+                let body =
+                    keyring.open_text(note.key_id.as_deref(), NOTE_CONTEXT, note.body.clone())?;
+                let body = keyring.seal_text(NOTE_CONTEXT, &body);
+                sqlx::query!(
+                    "UPDATE report_note SET body = ?1, key_id = ?2 WHERE id = ?3",
+                    body,
+                    key_id,
+                    note.id
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            let attachments =
+                ReportAttachment::reencrypt_batch(&mut tx, keyring, BATCH_SIZE).await?;
+            let counter_notices =
+                CounterNotice::reencrypt_batch(&mut tx, keyring, BATCH_SIZE).await?;
+            let flaggers = TrustedFlagger::reencrypt_batch(&mut tx, keyring, BATCH_SIZE).await?;
+            let emails = OutboxEntry::reencrypt_batch(&mut tx, keyring, BATCH_SIZE).await?;
+            tx.commit().await?;
+
+            let batch = (reports.len() + messages.len() + notes.len()) as u64
+                + attachments
+                + counter_notices
+                + flaggers
+                + emails;
+            if batch == 0 {
+                return Ok(rewritten);
+            }
+            rewritten += batch;
+        }
     }
 }
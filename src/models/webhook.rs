@@ -0,0 +1,195 @@
+use crate::error::Error;
+use crate::models::audit::{AuditContext, AuditEntry};
+use crate::models::now_millis;
+use crate::models::outbox::{retry_delay, OutboxStatus, CLAIM_MILLIS, MAX_ATTEMPTS};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListWebhookDeliveries {
+    pub status: Option<OutboxStatus>,
+}
+
+/// An event waiting to be posted to one endpoint, or given up on. Delivered
+/// events are removed.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub event_id: String,
+    pub event: String,
+    pub url: String,
+    pub status: OutboxStatus,
+    pub attempts: i64,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+}
+
+/// An event claimed for delivery.
+pub struct QueuedWebhook {
+    pub id: i64,
+    pub event_id: String,
+    pub event: String,
+    pub url: String,
+    pub payload: String,
+    pub attempts: i64,
+}
+
+impl WebhookDelivery {
+    /// Queues an event for an endpoint. Called within the transaction that
+    /// caused it, so the event is sent if and only if the change is committed.
+    pub(crate) async fn enqueue(
+        conn: &mut SqliteConnection,
+        event_id: &str,
+        event: &str,
+        url: &str,
+        payload: &str,
+    ) -> Result<i64, Error> {
+        let now = now_millis();
+        let id = sqlx::query!(
+            "INSERT INTO webhook_delivery
+                ( event_id, event, url, payload, next_attempt_at, created_at, updated_at )
+                VALUES ( ?1, ?2, ?3, ?4, ?5, ?5, ?5 )",
+            event_id,
+            event,
+            url,
+            payload,
+            now
+        )
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    /// Claims up to `limit` pending events that are due. With `only_new`,
+    /// events that have already failed are left to the background worker.
+    pub(crate) async fn claim(
+        pool: &SqlitePool,
+        only_new: bool,
+        limit: i64,
+    ) -> Result<Vec<QueuedWebhook>, Error> {
+        let now = now_millis();
+        let claimed_until = now + CLAIM_MILLIS;
+        let webhooks = sqlx::query_as!(
+            QueuedWebhook,
+            r#"UPDATE webhook_delivery SET next_attempt_at = ?1
+                WHERE id IN (
+                    SELECT id FROM webhook_delivery
+                        WHERE status = ?2 AND next_attempt_at <= ?3 AND (NOT ?4 OR attempts = 0)
+                        ORDER BY next_attempt_at
+                        LIMIT ?5
+                )
+                RETURNING id AS "id!", event_id, event, url, payload, attempts"#,
+            claimed_until,
+            OutboxStatus::Pending,
+            now,
+            only_new,
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(webhooks)
+    }
+
+    pub(crate) async fn delivered(pool: &SqlitePool, id: i64) -> Result<(), Error> {
+        sqlx::query!("DELETE FROM webhook_delivery WHERE id = ?", id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Schedules the next attempt with exponential backoff, or dead-letters
+    /// the event once it has failed `MAX_ATTEMPTS` times.
+    pub(crate) async fn failed(
+        pool: &SqlitePool,
+        webhook: &QueuedWebhook,
+        error: &str,
+    ) -> Result<(), Error> {
+        let now = now_millis();
+        let attempts = webhook.attempts + 1;
+        let status = if attempts >= MAX_ATTEMPTS {
+            OutboxStatus::Dead
+        } else {
+            OutboxStatus::Pending
+        };
+        let next_attempt_at = now + retry_delay(attempts);
+        sqlx::query!(
+            "UPDATE webhook_delivery
+                SET status = ?1, attempts = ?2, next_attempt_at = ?3, last_error = ?4, updated_at = ?5
+                WHERE id = ?6",
+            status,
+            attempts,
+            next_attempt_at,
+            error,
+            now,
+            webhook.id
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list(
+        pool: &SqlitePool,
+        query: ListWebhookDeliveries,
+    ) -> Result<Vec<WebhookDelivery>, Error> {
+        let deliveries = sqlx::query_as!(
+            WebhookDelivery,
+            r#"SELECT id AS "id!",
+                      event_id,
+                      event,
+                      url,
+                      status AS "status: OutboxStatus",
+                      attempts,
+                      next_attempt_at,
+                      last_error,
+                      created_at
+                FROM webhook_delivery
+                WHERE ?1 IS NULL OR status = ?1
+                ORDER BY id"#,
+            query.status
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(deliveries)
+    }
+
+    /// Puts an event back in line for immediate delivery with a fresh set of
+    /// attempts, typically once the endpoint is back up.
+    pub async fn retry(pool: &SqlitePool, id: i64, ctx: &AuditContext) -> Result<(), Error> {
+        let mut tx = pool.begin().await?;
+        let now = now_millis();
+        let result = sqlx::query!(
+            "UPDATE webhook_delivery
+                SET status = ?1, attempts = 0, next_attempt_at = ?2, updated_at = ?2
+                WHERE id = ?3",
+            OutboxStatus::Pending,
+            now,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound);
+        }
+        AuditEntry::append(&mut tx, ctx, "webhook.retry", &id.to_string()).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Deletes dead-lettered events last touched before `before`.
+    pub async fn purge_dead(pool: &SqlitePool, before: i64) -> Result<u64, Error> {
+        let purged = sqlx::query!(
+            "DELETE FROM webhook_delivery WHERE status = ?1 AND updated_at < ?2",
+            OutboxStatus::Dead,
+            before
+        )
+        .execute(pool)
+        .await?
+        .rows_affected();
+        Ok(purged)
+    }
+}
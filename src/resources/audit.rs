@@ -0,0 +1,68 @@
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Query};
+use axum::{extract::State, handler::Handler, routing::get, Json, Router};
+use axum_extra::headers::{self, authorization::Bearer};
+use axum_extra::TypedHeader;
+use hyper::http::request::Parts;
+use std::convert::Infallible;
+use tower_http::validate_request::ValidateRequestHeaderLayer;
+
+use crate::error::Error;
+use crate::models::audit::{AuditContext, AuditEntry, AuditVerification, ListAudit};
+use crate::models::Page;
+use crate::server::AppState;
+
+/// Header naming the operator behind an admin request. Every admin shares the
+/// same bearer token, so this is what tells entries in the audit log apart.
+pub const ACTOR_HEADER: &str = "x-actor";
+/// Header carrying the free-text reason recorded alongside an admin action.
+pub const REASON_HEADER: &str = "x-audit-reason";
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuditContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = |name| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        Ok(AuditContext {
+            actor: header(ACTOR_HEADER).unwrap_or_else(|| "admin".to_string()),
+            reason: header(REASON_HEADER),
+        })
+    }
+}
+
+async fn list_audit_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    Query(query): Query<ListAudit>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Page<AuditEntry>>, Error> {
+    let page = AuditEntry::list(&app_state.pool, query).await?;
+    Ok(Json(page))
+}
+
+async fn verify_audit_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    State(app_state): State<AppState>,
+) -> Result<Json<AuditVerification>, Error> {
+    let verification = AuditEntry::verify(&app_state.pool).await?;
+    Ok(Json(verification))
+}
+
+pub fn audit_routes(admin_token: &str) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/audit",
+            get(list_audit_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/audit/verify",
+            get(verify_audit_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+}
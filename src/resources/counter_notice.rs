@@ -0,0 +1,88 @@
+use axum::error_handling::HandleErrorLayer;
+use axum::{
+    extract::{Path, Query, State},
+    handler::Handler,
+    routing::{get, post, put},
+    BoxError, Json, Router,
+};
+use axum_extra::headers::{self, authorization::Bearer};
+use axum_extra::TypedHeader;
+use governor::clock::QuantaInstant;
+use governor::middleware::NoOpMiddleware;
+use std::rc::Rc;
+
+use tower::ServiceBuilder;
+use tower_governor::key_extractor::SmartIpKeyExtractor;
+use tower_governor::{governor::GovernorConfig, GovernorLayer};
+use tower_http::validate_request::ValidateRequestHeaderLayer;
+
+use crate::error::{Error, ErrorMessage};
+use crate::models::audit::AuditContext;
+use crate::models::counter_notice::{
+    CounterNotice, CreateCounterNotice, ListCounterNotices, SetLegalAction,
+};
+use crate::server::AppState;
+
+async fn create_counter_notice_handler(
+    Path(paste_id): Path<String>,
+    State(app_state): State<AppState>,
+    Json(payload): Json<CreateCounterNotice>,
+) -> Result<Json<CounterNotice>, Error> {
+    let notice = CounterNotice::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        paste_id,
+        payload,
+    )
+    .await?;
+    Ok(Json(notice))
+}
+
+async fn list_counter_notice_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    Query(query): Query<ListCounterNotices>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<CounterNotice>>, Error> {
+    let notices =
+        CounterNotice::list(&app_state.pool, &app_state.report_settings.keyring, query).await?;
+    Ok(Json(notices))
+}
+
+async fn set_legal_action_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<i64>,
+    State(app_state): State<AppState>,
+    Json(payload): Json<SetLegalAction>,
+) -> Result<Json<()>, Error> {
+    CounterNotice::set_legal_action(&app_state.pool, id, payload, &ctx).await?;
+    Ok(Json(()))
+}
+
+pub fn counter_notice_routes(
+    admin_token: &str,
+    governor_config: Box<Rc<GovernorConfig<SmartIpKeyExtractor, NoOpMiddleware<QuantaInstant>>>>,
+) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/counter-notice",
+            get(list_counter_notice_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/counter-notice/:id/legal-action",
+            put(set_legal_action_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/paste/:id/counter-notice",
+            post(create_counter_notice_handler).layer(
+                ServiceBuilder::new()
+                    .layer(HandleErrorLayer::new(|e: BoxError| async move {
+                        Json(ErrorMessage { msg: e.to_string() })
+                    }))
+                    .layer(GovernorLayer {
+                        config: Box::leak(governor_config),
+                    }),
+            ),
+        )
+}
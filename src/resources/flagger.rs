@@ -0,0 +1,162 @@
+use axum::{
+    extract::{DefaultBodyLimit, Path, Query, Request, State},
+    handler::Handler,
+    middleware::{self, Next},
+    response::Response,
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use axum_extra::headers::{self, authorization::Bearer};
+use axum_extra::TypedHeader;
+use governor::{DefaultKeyedRateLimiter, Quota, RateLimiter};
+use std::net::IpAddr;
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+use tower_governor::key_extractor::{KeyExtractor, SmartIpKeyExtractor};
+use tower_http::validate_request::ValidateRequestHeaderLayer;
+
+use crate::error::Error;
+use crate::models::attachment::{MAX_ATTACHMENTS, MAX_ATTACHMENT_SIZE};
+use crate::models::audit::AuditContext;
+use crate::models::flagger::{
+    CreateTrustedFlagger, IssuedTrustedFlagger, ListTrustedFlaggers, TrustedFlagger,
+};
+use crate::models::report::{CreateFlaggedReport, FlaggedReport, Report};
+use crate::server::AppState;
+
+async fn list_flagger_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    Query(query): Query<ListTrustedFlaggers>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<TrustedFlagger>>, Error> {
+    let flaggers =
+        TrustedFlagger::list(&app_state.pool, &app_state.report_settings.keyring, query).await?;
+    Ok(Json(flaggers))
+}
+
+async fn create_flagger_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    State(app_state): State<AppState>,
+    Json(payload): Json<CreateTrustedFlagger>,
+) -> Result<Json<IssuedTrustedFlagger>, Error> {
+    let issued = TrustedFlagger::create(
+        &app_state.pool,
+        &app_state.report_settings.keyring,
+        payload,
+        &ctx,
+    )
+    .await?;
+    Ok(Json(issued))
+}
+
+async fn revoke_flagger_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<i64>,
+    State(app_state): State<AppState>,
+) -> Result<Json<()>, Error> {
+    TrustedFlagger::revoke(&app_state.pool, id, &ctx).await?;
+    Ok(Json(()))
+}
+
+/// Request budgets for the flagger report endpoint. Trusted flaggers report
+/// at volume, so each key gets a generous budget of its own, while callers
+/// without a valid key get the strict per-IP budget of the public forms.
+pub struct FlaggerRateLimits {
+    flaggers: DefaultKeyedRateLimiter<i64>,
+    public: DefaultKeyedRateLimiter<IpAddr>,
+}
+
+impl Default for FlaggerRateLimits {
+    fn default() -> Self {
+        let quota = |period, burst| {
+            Quota::with_period(period)
+                .unwrap()
+                .allow_burst(NonZeroU32::new(burst).unwrap())
+        };
+        FlaggerRateLimits {
+            flaggers: RateLimiter::keyed(quota(Duration::from_millis(100), 50)),
+            public: RateLimiter::keyed(quota(Duration::from_secs(4), 2)),
+        }
+    }
+}
+
+/// Authenticates the flagger before rate limiting, so the budget follows
+/// the API key rather than the address it is used from.
+async fn limit_flagged_reports(
+    State(app_state): State<AppState>,
+    TypedHeader(auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, Error> {
+    let limits = &app_state.flagger_rate_limits;
+    match TrustedFlagger::authenticate(
+        &app_state.pool,
+        &app_state.report_settings.keyring,
+        auth_header.token(),
+    )
+    .await
+    {
+        Ok(flagger) => {
+            if limits.flaggers.check_key(&flagger.id).is_err() {
+                return Err(Error::TooManyRequests);
+            }
+            request.extensions_mut().insert(flagger);
+            Ok(next.run(request).await)
+        }
+        Err(error) => {
+            if let Ok(ip) = SmartIpKeyExtractor.extract(&request) {
+                if limits.public.check_key(&ip).is_err() {
+                    return Err(Error::TooManyRequests);
+                }
+            }
+            Err(error)
+        }
+    }
+}
+
+/// Machine-to-machine report endpoint. The bearer token is the flagger's
+/// API key, not the admin token.
+async fn create_flagged_report_handler(
+    Extension(flagger): Extension<TrustedFlagger>,
+    State(app_state): State<AppState>,
+    Json(payload): Json<CreateFlaggedReport>,
+) -> Result<Json<FlaggedReport>, Error> {
+    let report = Report::create_flagged(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.webhooks,
+        &app_state.report_settings,
+        &flagger,
+        payload,
+    )
+    .await?;
+    Ok(Json(report))
+}
+
+pub fn flagger_routes(admin_token: &str, app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/flagger",
+            get(list_flagger_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))).post(
+                create_flagger_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token)),
+            ),
+        )
+        .route(
+            "/api/flagger/:id",
+            delete(revoke_flagger_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/flagger/reports",
+            post(create_flagged_report_handler)
+                .layer(DefaultBodyLimit::max(
+                    MAX_ATTACHMENTS * MAX_ATTACHMENT_SIZE * 4 / 3 + 1024 * 1024,
+                ))
+                .layer(middleware::from_fn_with_state(
+                    app_state,
+                    limit_flagged_reports,
+                )),
+        )
+}
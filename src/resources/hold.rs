@@ -0,0 +1,56 @@
+use axum::{
+    extract::{Path, Query, State},
+    handler::Handler,
+    routing::{delete, get},
+    Json, Router,
+};
+use axum_extra::headers::{self, authorization::Bearer};
+use axum_extra::TypedHeader;
+use tower_http::validate_request::ValidateRequestHeaderLayer;
+
+use crate::error::Error;
+use crate::models::audit::AuditContext;
+use crate::models::hold::{CreateLegalHold, LegalHold, ListLegalHolds};
+use crate::server::AppState;
+
+async fn list_hold_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    Query(query): Query<ListLegalHolds>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<LegalHold>>, Error> {
+    let holds = LegalHold::list(&app_state.pool, query).await?;
+    Ok(Json(holds))
+}
+
+async fn create_hold_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    State(app_state): State<AppState>,
+    Json(payload): Json<CreateLegalHold>,
+) -> Result<Json<LegalHold>, Error> {
+    let hold = LegalHold::create(&app_state.pool, payload, &ctx).await?;
+    Ok(Json(hold))
+}
+
+async fn lift_hold_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<i64>,
+    State(app_state): State<AppState>,
+) -> Result<Json<()>, Error> {
+    LegalHold::lift(&app_state.pool, id, &ctx).await?;
+    Ok(Json(()))
+}
+
+pub fn hold_routes(admin_token: &str) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/hold",
+            get(list_hold_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token)))
+                .post(create_hold_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/hold/:id",
+            delete(lift_hold_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+}
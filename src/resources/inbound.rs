@@ -0,0 +1,86 @@
+use axum::{
+    body::{to_bytes, Bytes},
+    extract::{FromRequest, Multipart, Request, State},
+    handler::Handler,
+    http::header::CONTENT_TYPE,
+    routing::post,
+    Json, Router,
+};
+use axum_extra::headers::{self, authorization::Bearer};
+use axum_extra::TypedHeader;
+use tower_http::validate_request::ValidateRequestHeaderLayer;
+
+use crate::error::{Error, FieldError};
+use crate::models::inbound::InboundEmail;
+use crate::server::AppState;
+
+const MAX_INBOUND_SIZE: usize = 10 * 1024 * 1024;
+
+fn unreadable() -> Error {
+    Error::Validation(vec![FieldError::new("email", "UNREADABLE_EMAIL")])
+}
+
+/// Reads the fields of a SendGrid Inbound Parse POST. When the raw message
+/// was posted as well it is preferred over the pre-parsed fields.
+async fn from_multipart(mut multipart: Multipart) -> Result<InboundEmail, Error> {
+    let (mut raw, mut from, mut subject, mut text) = (None, None, None, None);
+    while let Some(field) = multipart.next_field().await.map_err(|_| unreadable())? {
+        let name = field.name().unwrap_or_default().to_string();
+        let value: Bytes = field.bytes().await.map_err(|_| unreadable())?;
+        match name.as_str() {
+            "email" => raw = Some(value),
+            "from" => from = Some(String::from_utf8_lossy(&value).into_owned()),
+            "subject" => subject = Some(String::from_utf8_lossy(&value).into_owned()),
+            "text" => text = Some(String::from_utf8_lossy(&value).into_owned()),
+            _ => (),
+        }
+    }
+    if let Some(raw) = raw {
+        return InboundEmail::from_rfc822(&raw).ok_or_else(unreadable);
+    }
+    let from = from.ok_or_else(unreadable)?;
+    Ok(InboundEmail::from_fields(
+        &from,
+        &subject.unwrap_or_default(),
+        &text.unwrap_or_default(),
+    ))
+}
+
+async fn inbound_email_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    State(app_state): State<AppState>,
+    request: Request,
+) -> Result<Json<()>, Error> {
+    let is_multipart = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.starts_with("multipart/form-data"))
+        .unwrap_or(false);
+    let email = if is_multipart {
+        let multipart = Multipart::from_request(request, &app_state)
+            .await
+            .map_err(|_| unreadable())?;
+        from_multipart(multipart).await?
+    } else {
+        let raw = to_bytes(request.into_body(), MAX_INBOUND_SIZE)
+            .await
+            .map_err(|_| unreadable())?;
+        InboundEmail::from_rfc822(&raw).ok_or_else(unreadable)?
+    };
+    email
+        .ingest(
+            &app_state.pool,
+            &app_state.mailer,
+            &app_state.report_settings,
+        )
+        .await?;
+    Ok(Json(()))
+}
+
+pub fn inbound_routes(admin_token: &str) -> Router<AppState> {
+    Router::new().route(
+        "/api/inbound-email",
+        post(inbound_email_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+    )
+}
@@ -0,0 +1,47 @@
+use axum::{
+    extract::{Path, Query, State},
+    handler::Handler,
+    routing::get,
+    Json, Router,
+};
+use axum_extra::headers::{self, authorization::Bearer};
+use axum_extra::TypedHeader;
+use serde::Deserialize;
+use tower_http::validate_request::ValidateRequestHeaderLayer;
+
+use crate::error::Error;
+use crate::mailer::templates::{RenderedEmail, TEMPLATES};
+use crate::server::AppState;
+
+async fn list_templates_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+) -> Json<&'static [&'static str]> {
+    Json(TEMPLATES)
+}
+
+#[derive(Deserialize)]
+struct PreviewTemplate {
+    locale: Option<String>,
+}
+
+async fn preview_template_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    Path(name): Path<String>,
+    Query(query): Query<PreviewTemplate>,
+    State(app_state): State<AppState>,
+) -> Result<Json<RenderedEmail>, Error> {
+    let email = app_state.mailer.preview(&name, query.locale.as_deref())?;
+    Ok(Json(email))
+}
+
+pub fn mail_routes(admin_token: &str) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/mail/templates",
+            get(list_templates_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/mail/templates/:name",
+            get(preview_template_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+}
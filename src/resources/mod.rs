@@ -1,2 +1,11 @@
+pub mod audit;
+pub mod counter_notice;
+pub mod flagger;
+pub mod hold;
+pub mod inbound;
+pub mod mail;
+pub mod outbox;
 pub mod paste;
 pub mod report;
+pub mod trash;
+pub mod webhook;
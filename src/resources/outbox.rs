@@ -0,0 +1,47 @@
+use axum::{
+    extract::{Path, Query, State},
+    handler::Handler,
+    routing::{get, post},
+    Json, Router,
+};
+use axum_extra::headers::{self, authorization::Bearer};
+use axum_extra::TypedHeader;
+use tower_http::validate_request::ValidateRequestHeaderLayer;
+
+use crate::error::Error;
+use crate::models::audit::AuditContext;
+use crate::models::outbox::{ListOutbox, OutboxEntry};
+use crate::server::AppState;
+
+async fn list_outbox_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    Query(query): Query<ListOutbox>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<OutboxEntry>>, Error> {
+    let entries =
+        OutboxEntry::list(&app_state.pool, &app_state.report_settings.keyring, query).await?;
+    Ok(Json(entries))
+}
+
+async fn retry_outbox_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<i64>,
+    State(app_state): State<AppState>,
+) -> Result<Json<()>, Error> {
+    OutboxEntry::retry(&app_state.pool, id, &ctx).await?;
+    app_state.mailer.notify();
+    Ok(Json(()))
+}
+
+pub fn outbox_routes(admin_token: &str) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/outbox",
+            get(list_outbox_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/outbox/:id/retry",
+            post(retry_outbox_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+}
@@ -1,10 +1,10 @@
 use axum::error_handling::HandleErrorLayer;
 use axum::response::IntoResponse;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     handler::Handler,
     response::AppendHeaders,
-    routing::{get, post},
+    routing::{get, post, put},
     BoxError, Json, Router,
 };
 use axum_extra::headers::{self, authorization::Bearer};
@@ -13,14 +13,17 @@ use governor::clock::QuantaInstant;
 use governor::middleware::NoOpMiddleware;
 use hyper::header::CACHE_CONTROL;
 use std::rc::Rc;
-use std::time::{SystemTime, UNIX_EPOCH};
 use tower::ServiceBuilder;
 use tower_governor::key_extractor::SmartIpKeyExtractor;
 use tower_governor::{governor::GovernorConfig, GovernorLayer};
-use tower_http::auth::add_authorization::AddAuthorizationLayer;
+use tower_http::validate_request::ValidateRequestHeaderLayer;
 
 use crate::error::{Error, ErrorMessage};
-use crate::models::paste::{CreatePaste, Paste, UpdatePaste};
+use crate::models::audit::AuditContext;
+use crate::models::paste::{
+    CreatePaste, ListPastes, Paste, PasteDetails, PasteSummary, UpdatePaste, UpdatePasteStatus,
+};
+use crate::models::{now_millis, Page};
 use crate::server::AppState;
 
 async fn create_paste_handler(
@@ -31,22 +34,24 @@ async fn create_paste_handler(
     Ok(Json(()))
 }
 
+/// How long a browser may keep a paste it has seen. Shared caches never
+/// keep one, so takedowns and deletions apply within this time.
+const MAX_CACHE_SECONDS: i64 = 60;
+
 async fn view_paste_handler(
     Path(id): Path<String>,
     State(app_state): State<AppState>,
 ) -> Result<impl IntoResponse, Error> {
     let paste = Paste::view(&app_state.pool, id).await?;
     if paste.expiry_views.is_none() {
-        let now = SystemTime::now();
         let max_age = match paste.expiry_time {
-            Some(expires) => {
-                let max_age = expires - now.duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
-                format!("public, max-age={}", max_age)
-            }
-            None => "public, max-age=3600".to_string(),
+            Some(expires) => ((expires - now_millis()) / 1000).clamp(0, MAX_CACHE_SECONDS),
+            None => MAX_CACHE_SECONDS,
         };
-
-        return Ok((AppendHeaders([(CACHE_CONTROL, max_age)]), Json(paste)));
+        return Ok((
+            AppendHeaders([(CACHE_CONTROL, format!("private, max-age={}", max_age))]),
+            Json(paste),
+        ));
     }
     Ok((
         AppendHeaders([(CACHE_CONTROL, "no-cache".to_string())]),
@@ -56,25 +61,76 @@ async fn view_paste_handler(
 
 async fn update_paste_handler(
     TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
     Path(id): Path<String>,
     State(app_state): State<AppState>,
     Json(payload): Json<UpdatePaste>,
 ) -> Result<Json<()>, Error> {
-    Paste::update(&app_state.pool, id, payload).await?;
+    Paste::update(&app_state.pool, id, payload, &ctx).await?;
     Ok(Json(()))
 }
 
 async fn delete_paste_handler(
     TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<String>,
+    State(app_state): State<AppState>,
+) -> Result<Json<()>, Error> {
+    Paste::delete(&app_state.pool, &app_state.webhooks, id, &ctx).await?;
+    Ok(Json(()))
+}
+
+async fn list_paste_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    Query(query): Query<ListPastes>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Page<PasteSummary>>, Error> {
+    let pastes = Paste::list(&app_state.pool, query).await?;
+    Ok(Json(pastes))
+}
+
+async fn inspect_paste_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    Path(id): Path<String>,
+    State(app_state): State<AppState>,
+) -> Result<Json<PasteDetails>, Error> {
+    let paste = Paste::inspect(&app_state.pool, id).await?;
+    Ok(Json(paste))
+}
+
+async fn pin_paste_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<String>,
+    State(app_state): State<AppState>,
+) -> Result<Json<()>, Error> {
+    Paste::set_pinned(&app_state.pool, id, true, &ctx).await?;
+    Ok(Json(()))
+}
+
+async fn unpin_paste_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
     Path(id): Path<String>,
     State(app_state): State<AppState>,
 ) -> Result<Json<()>, Error> {
-    Paste::delete(&app_state.pool, id).await?;
+    Paste::set_pinned(&app_state.pool, id, false, &ctx).await?;
+    Ok(Json(()))
+}
+
+async fn update_paste_status_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<String>,
+    State(app_state): State<AppState>,
+    Json(payload): Json<UpdatePasteStatus>,
+) -> Result<Json<()>, Error> {
+    Paste::set_status(&app_state.pool, &app_state.webhooks, id, payload, &ctx).await?;
     Ok(Json(()))
 }
 
 pub fn paste_routes(
-    admin_token: &String,
+    admin_token: &str,
     governor_config: Box<Rc<GovernorConfig<SmartIpKeyExtractor, NoOpMiddleware<QuantaInstant>>>>,
 ) -> Router<AppState> {
     Router::new()
@@ -93,7 +149,26 @@ pub fn paste_routes(
         .route(
             "/api/paste/:id",
             get(view_paste_handler)
-                .put(update_paste_handler.layer(AddAuthorizationLayer::bearer(&admin_token)))
-                .delete(delete_paste_handler.layer(AddAuthorizationLayer::bearer(&admin_token))),
+                .put(update_paste_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token)))
+                .delete(
+                    delete_paste_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token)),
+                ),
+        )
+        .route(
+            "/api/paste",
+            get(list_paste_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/paste/:id/inspect",
+            get(inspect_paste_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/paste/:id/pin",
+            put(pin_paste_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token)))
+                .delete(unpin_paste_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/paste/:id/status",
+            put(update_paste_status_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
         )
 }
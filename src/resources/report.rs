@@ -1,8 +1,11 @@
 use axum::error_handling::HandleErrorLayer;
 use axum::{
-    extract::{Path, State},
+    extract::{DefaultBodyLimit, Path, Query, State},
     handler::Handler,
-    routing::{delete, get, post},
+    http::header::{ACCEPT_LANGUAGE, CONTENT_DISPOSITION, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS},
+    http::HeaderMap,
+    response::IntoResponse,
+    routing::{delete, get, post, put},
     BoxError, Json, Router,
 };
 use axum_extra::headers::{self, authorization::Bearer};
@@ -14,53 +17,268 @@ use std::rc::Rc;
 use tower::ServiceBuilder;
 use tower_governor::key_extractor::SmartIpKeyExtractor;
 use tower_governor::{governor::GovernorConfig, GovernorLayer};
-use tower_http::auth::add_authorization::AddAuthorizationLayer;
+use tower_http::validate_request::ValidateRequestHeaderLayer;
 
 use crate::error::{Error, ErrorMessage};
-use crate::models::report::{CreateReport, Report};
+use crate::models::attachment::{ReportAttachment, MAX_ATTACHMENTS, MAX_ATTACHMENT_SIZE};
+use crate::models::audit::AuditContext;
+use crate::models::report::{
+    AssignReport, CreateReport, CreateReportNote, CreateReportReply, ListReports, LookupTicket,
+    PasteReports, Report, ReportCase, ReportCorrespondence, ReportNote, ReportTicket,
+    RetentionCandidate, TransitionReport,
+};
+use crate::models::{now_millis, Page, DAY_MILLIS};
 use crate::server::AppState;
 
 async fn list_report_handler(
+    Query(query): Query<ListReports>,
     State(app_state): State<AppState>,
-) -> Result<Json<Vec<Report>>, Error> {
-    let reports = Report::list(&app_state.pool).await?;
+) -> Result<Json<Page<Report>>, Error> {
+    let reports = Report::list(&app_state.pool, &app_state.report_settings.keyring, query).await?;
     Ok(Json(reports))
 }
 
 async fn create_report_handler(
     State(app_state): State<AppState>,
-    Json(payload): Json<CreateReport>,
+    headers: HeaderMap,
+    Json(mut payload): Json<CreateReport>,
+) -> Result<Json<()>, Error> {
+    if payload.locale.is_none() {
+        payload.locale = headers
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+    }
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        payload,
+    )
+    .await?;
+    Ok(Json(()))
+}
+
+async fn verify_report_handler(
+    Path(token): Path<String>,
+    State(app_state): State<AppState>,
 ) -> Result<Json<()>, Error> {
-    Report::create(&app_state.pool, &app_state.mailer, payload).await?;
+    Report::verify(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.webhooks,
+        &app_state.report_settings,
+        token,
+    )
+    .await?;
     Ok(Json(()))
 }
 
+async fn lookup_report_handler(
+    State(app_state): State<AppState>,
+    Json(payload): Json<LookupTicket>,
+) -> Result<Json<ReportTicket>, Error> {
+    let ticket =
+        Report::lookup(&app_state.pool, &app_state.report_settings.keyring, payload).await?;
+    Ok(Json(ticket))
+}
+
+async fn list_report_by_paste_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<PasteReports>>, Error> {
+    let reports = Report::by_paste(&app_state.pool).await?;
+    Ok(Json(reports))
+}
+
+/// Dry run of the retention task: lists the reports it would anonymise now.
+async fn list_report_retention_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<RetentionCandidate>>, Error> {
+    let closed_before = now_millis() - app_state.report_settings.pii_retention_days * DAY_MILLIS;
+    let candidates = Report::retention_due(&app_state.pool, closed_before).await?;
+    Ok(Json(candidates))
+}
+
 async fn delete_report_handler(
     TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
     Path(id): Path<String>,
     State(app_state): State<AppState>,
 ) -> Result<Json<()>, Error> {
-    Report::delete(&app_state.pool, id).await?;
+    Report::delete(&app_state.pool, id, &ctx).await?;
     Ok(Json(()))
 }
 
+async fn get_report_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    Path(id): Path<i64>,
+    State(app_state): State<AppState>,
+) -> Result<Json<ReportCase>, Error> {
+    let report = Report::get(&app_state.pool, &app_state.report_settings.keyring, id).await?;
+    Ok(Json(report))
+}
+
+async fn transition_report_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<i64>,
+    State(app_state): State<AppState>,
+    Json(payload): Json<TransitionReport>,
+) -> Result<Json<()>, Error> {
+    Report::transition(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings.keyring,
+        id,
+        payload,
+        &ctx,
+    )
+    .await?;
+    Ok(Json(()))
+}
+
+async fn assign_report_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<i64>,
+    State(app_state): State<AppState>,
+    Json(payload): Json<AssignReport>,
+) -> Result<Json<()>, Error> {
+    Report::assign(&app_state.pool, id, payload, &ctx).await?;
+    Ok(Json(()))
+}
+
+async fn create_report_note_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<i64>,
+    State(app_state): State<AppState>,
+    Json(payload): Json<CreateReportNote>,
+) -> Result<Json<ReportNote>, Error> {
+    let note = Report::add_note(
+        &app_state.pool,
+        &app_state.report_settings.keyring,
+        id,
+        payload,
+        &ctx,
+    )
+    .await?;
+    Ok(Json(note))
+}
+
+async fn create_report_reply_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<i64>,
+    State(app_state): State<AppState>,
+    Json(payload): Json<CreateReportReply>,
+) -> Result<Json<ReportCorrespondence>, Error> {
+    let message = Report::reply(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings.keyring,
+        id,
+        payload,
+        &ctx,
+    )
+    .await?;
+    Ok(Json(message))
+}
+
+/// Serves an attachment as a download so it is never rendered inline in
+/// the admin panel.
+async fn get_report_attachment_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    Path((id, attachment_id)): Path<(i64, i64)>,
+    State(app_state): State<AppState>,
+) -> Result<impl IntoResponse, Error> {
+    let attachment = ReportAttachment::content(
+        &app_state.pool,
+        &app_state.report_settings.keyring,
+        id,
+        attachment_id,
+    )
+    .await?;
+    Ok((
+        [
+            (CONTENT_TYPE, attachment.content_type),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", attachment.filename),
+            ),
+            (X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        attachment.content,
+    ))
+}
+
 pub fn report_routes(
-    admin_token: &String,
+    admin_token: &str,
     governor_config: Box<Rc<GovernorConfig<SmartIpKeyExtractor, NoOpMiddleware<QuantaInstant>>>>,
 ) -> Router<AppState> {
     Router::new()
         .route(
             "/api/report/:id",
-            delete(delete_report_handler).layer(AddAuthorizationLayer::bearer(&admin_token)),
+            delete(delete_report_handler).layer(ValidateRequestHeaderLayer::bearer(admin_token)),
+        )
+        .route(
+            "/api/report/:id",
+            get(get_report_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/report/:id/transition",
+            post(transition_report_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/report/:id/assignee",
+            put(assign_report_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/report/:id/notes",
+            post(create_report_note_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/report/:id/attachments/:attachment_id",
+            get(get_report_attachment_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/report/:id/replies",
+            post(create_report_reply_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route("/api/report/verify/:token", post(verify_report_handler))
+        .route(
+            "/api/report/status",
+            post(lookup_report_handler).layer(
+                ServiceBuilder::new()
+                    .layer(HandleErrorLayer::new(|e: BoxError| async move {
+                        Json(ErrorMessage { msg: e.to_string() })
+                    }))
+                    .layer(GovernorLayer {
+                        config: Box::leak(governor_config.clone()),
+                    }),
+            ),
+        )
+        .route(
+            "/api/report/by-paste",
+            get(list_report_by_paste_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/report/retention",
+            get(list_report_retention_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
         )
         .route(
             "/api/report",
-            get(list_report_handler.layer(AddAuthorizationLayer::bearer(&admin_token))),
+            get(list_report_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
         )
         .route(
             "/api/report",
             post(create_report_handler).layer(
                 ServiceBuilder::new()
+                    .layer(DefaultBodyLimit::max(
+                        MAX_ATTACHMENTS * MAX_ATTACHMENT_SIZE * 4 / 3 + 1024 * 1024,
+                    ))
                     .layer(HandleErrorLayer::new(|e: BoxError| async move {
                         Json(ErrorMessage { msg: e.to_string() })
                     }))
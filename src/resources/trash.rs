@@ -0,0 +1,68 @@
+use axum::{
+    extract::{Path, State},
+    handler::Handler,
+    routing::{get, post},
+    Json, Router,
+};
+use axum_extra::headers::{self, authorization::Bearer};
+use axum_extra::TypedHeader;
+use serde::Serialize;
+use tower_http::validate_request::ValidateRequestHeaderLayer;
+
+use crate::error::Error;
+use crate::models::audit::AuditContext;
+use crate::models::paste::{Paste, TrashedPaste};
+use crate::models::report::{Report, TrashedReport};
+use crate::server::AppState;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Trash {
+    pastes: Vec<TrashedPaste>,
+    reports: Vec<TrashedReport>,
+}
+
+async fn list_trash_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Trash>, Error> {
+    let pastes = Paste::trashed(&app_state.pool).await?;
+    let reports = Report::trashed(&app_state.pool).await?;
+    Ok(Json(Trash { pastes, reports }))
+}
+
+async fn restore_paste_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<String>,
+    State(app_state): State<AppState>,
+) -> Result<Json<()>, Error> {
+    Paste::restore(&app_state.pool, id, &ctx).await?;
+    Ok(Json(()))
+}
+
+async fn restore_report_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<String>,
+    State(app_state): State<AppState>,
+) -> Result<Json<()>, Error> {
+    Report::restore(&app_state.pool, id, &ctx).await?;
+    Ok(Json(()))
+}
+
+pub fn trash_routes(admin_token: &str) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/trash",
+            get(list_trash_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/trash/paste/:id/restore",
+            post(restore_paste_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/trash/report/:id/restore",
+            post(restore_report_handler.layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+}
@@ -0,0 +1,50 @@
+use axum::{
+    extract::{Path, Query, State},
+    handler::Handler,
+    routing::{get, post},
+    Json, Router,
+};
+use axum_extra::headers::{self, authorization::Bearer};
+use axum_extra::TypedHeader;
+use tower_http::validate_request::ValidateRequestHeaderLayer;
+
+use crate::error::Error;
+use crate::models::audit::AuditContext;
+use crate::models::webhook::{ListWebhookDeliveries, WebhookDelivery};
+use crate::server::AppState;
+
+async fn list_webhook_deliveries_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    Query(query): Query<ListWebhookDeliveries>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<WebhookDelivery>>, Error> {
+    let deliveries = WebhookDelivery::list(&app_state.pool, query).await?;
+    Ok(Json(deliveries))
+}
+
+async fn retry_webhook_delivery_handler(
+    TypedHeader(_auth_header): TypedHeader<headers::Authorization<Bearer>>,
+    ctx: AuditContext,
+    Path(id): Path<i64>,
+    State(app_state): State<AppState>,
+) -> Result<Json<()>, Error> {
+    WebhookDelivery::retry(&app_state.pool, id, &ctx).await?;
+    app_state.webhooks.notify();
+    Ok(Json(()))
+}
+
+pub fn webhook_routes(admin_token: &str) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/webhooks/deliveries",
+            get(list_webhook_deliveries_handler
+                .layer(ValidateRequestHeaderLayer::bearer(admin_token))),
+        )
+        .route(
+            "/api/webhooks/deliveries/:id/retry",
+            post(
+                retry_webhook_delivery_handler
+                    .layer(ValidateRequestHeaderLayer::bearer(admin_token)),
+            ),
+        )
+}
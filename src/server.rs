@@ -6,7 +6,7 @@ use axum::{
 
 use hyper::http::request::Parts;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
-use std::{net::SocketAddr, rc::Rc, str::FromStr};
+use std::{collections::HashMap, net::SocketAddr, path::PathBuf, rc::Rc, str::FromStr, sync::Arc};
 use tracing_subscriber::{prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt};
 
 use tower_governor::{governor::GovernorConfigBuilder, key_extractor::SmartIpKeyExtractor};
@@ -18,21 +18,72 @@ use tower_http::{
 
 use anyhow::Result;
 
-use crate::{mailer::Mailer, resources::paste::paste_routes, resources::report::report_routes};
+use crate::{
+    crypto::Keyring,
+    mailer::{templates::Templates, transport::MailTransportConfig, Mailer},
+    models::digest::DigestSettings,
+    models::report::{Report, ReportCategory, ReportSettings},
+    resources::audit::audit_routes,
+    resources::counter_notice::counter_notice_routes,
+    resources::flagger::{flagger_routes, FlaggerRateLimits},
+    resources::hold::hold_routes,
+    resources::inbound::inbound_routes,
+    resources::mail::mail_routes,
+    resources::outbox::outbox_routes,
+    resources::paste::paste_routes,
+    resources::report::report_routes,
+    resources::trash::trash_routes,
+    resources::webhook::webhook_routes,
+    tasks::run_counter_notice_restorer,
+    tasks::run_digest_scheduler,
+    tasks::run_outbox_worker,
+    tasks::run_report_anonymiser,
+    tasks::run_report_expirer,
+    tasks::run_trash_purger,
+    tasks::run_webhook_worker,
+    webhooks::Webhooks,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: SqlitePool,
     pub mailer: Mailer,
+    pub webhooks: Webhooks,
+    pub report_settings: ReportSettings,
+    pub flagger_rate_limits: Arc<FlaggerRateLimits>,
 }
 
 pub struct Config {
     pub db_url: String,
     pub frontend_origin: String,
     pub admin_token: String,
-    pub sendgrid_api_key: String,
+    pub mail_transport: MailTransportConfig,
     pub email_from: String,
     pub email_name: String,
+    /// Directory whose files replace the built-in email templates.
+    pub email_templates_dir: Option<PathBuf>,
+    /// Keys encrypting reporters' personal data. The first one is active.
+    pub report_encryption_keys: Keyring,
+    /// Days a deleted paste or report stays in the trash before it is purged.
+    pub trash_retention_days: i64,
+    /// Number of distinct reports that quarantines a paste pending review.
+    pub auto_hide_report_threshold: Option<i64>,
+    /// Operators notified of new reports, per category.
+    pub report_recipients: HashMap<ReportCategory, Vec<String>>,
+    /// Whether operators get an email for every confirmed report.
+    pub operator_report_emails: bool,
+    /// When to send operators the daily moderation digest, if at all.
+    pub digest: Option<DigestSettings>,
+    /// Days a counter-notice waits for legal action before the paste is restored.
+    pub counter_notice_waiting_days: i64,
+    /// Hours a reporter has to confirm their email before the report expires.
+    pub report_verification_hours: i64,
+    /// Days a closed report keeps the reporter's email and message.
+    pub report_retention_days: i64,
+    /// Endpoints that moderation events are posted to.
+    pub webhook_urls: Vec<String>,
+    /// Secret the webhook signatures are keyed with.
+    pub webhook_secret: String,
 }
 
 async fn health_handler() -> Result<String, (StatusCode, String)> {
@@ -44,20 +95,50 @@ pub async fn get_app(
         db_url,
         frontend_origin,
         admin_token,
-        sendgrid_api_key,
+        mail_transport,
         email_from,
         email_name,
+        email_templates_dir,
+        report_encryption_keys,
+        auto_hide_report_threshold,
+        report_recipients,
+        operator_report_emails,
+        counter_notice_waiting_days,
+        report_verification_hours,
+        report_retention_days,
+        webhook_urls,
+        webhook_secret,
+        ..
     }: &Config,
 ) -> Result<(Router<AppState>, AppState)> {
-    let options = SqliteConnectOptions::from_str(&db_url)?.create_if_missing(true);
+    let options = SqliteConnectOptions::from_str(db_url)?.create_if_missing(true);
     let pool = SqlitePool::connect_with(options).await?;
     sqlx::migrate!("./migrations").run(&pool).await?;
     let mailer = Mailer::new(
-        sendgrid_api_key.to_string(),
+        mail_transport.build()?,
+        Templates::load(email_templates_dir.as_deref())?,
+        report_encryption_keys.clone(),
         email_from.to_string(),
         email_name.to_string(),
     );
-    let app_state = AppState { pool, mailer };
+    let webhooks = Webhooks::new(webhook_urls.clone(), webhook_secret.to_string())?;
+    let report_settings = ReportSettings {
+        keyring: report_encryption_keys.clone(),
+        frontend_origin: frontend_origin.to_string(),
+        verification_ttl_hours: *report_verification_hours,
+        auto_hide_threshold: *auto_hide_report_threshold,
+        category_recipients: report_recipients.clone(),
+        operator_report_emails: *operator_report_emails,
+        counter_notice_waiting_days: *counter_notice_waiting_days,
+        pii_retention_days: *report_retention_days,
+    };
+    let app_state = AppState {
+        pool,
+        mailer,
+        webhooks,
+        report_settings,
+        flagger_rate_limits: Arc::new(FlaggerRateLimits::default()),
+    };
 
     let governor_config = Box::new(Rc::new(
         GovernorConfigBuilder::default()
@@ -70,8 +151,17 @@ pub async fn get_app(
 
     let frontend_origin = frontend_origin.clone().into_bytes();
     let router = Router::new()
-        .merge(paste_routes(&admin_token, governor_config.clone()))
-        .merge(report_routes(&admin_token, governor_config))
+        .merge(paste_routes(admin_token, governor_config.clone()))
+        .merge(report_routes(admin_token, governor_config.clone()))
+        .merge(counter_notice_routes(admin_token, governor_config))
+        .merge(flagger_routes(admin_token, app_state.clone()))
+        .merge(audit_routes(admin_token))
+        .merge(mail_routes(admin_token))
+        .merge(outbox_routes(admin_token))
+        .merge(webhook_routes(admin_token))
+        .merge(trash_routes(admin_token))
+        .merge(hold_routes(admin_token))
+        .merge(inbound_routes(admin_token))
         .route("/", get(health_handler))
         .layer(TraceLayer::new_for_http())
         .layer(
@@ -98,6 +188,35 @@ pub async fn run_server(config: Config) -> Result<()> {
         .init();
 
     let (router, app_state) = get_app(&config).await?;
+    tokio::spawn(run_trash_purger(
+        app_state.pool.clone(),
+        config.trash_retention_days,
+    ));
+    tokio::spawn(run_counter_notice_restorer(app_state.pool.clone()));
+    tokio::spawn(run_report_expirer(
+        app_state.pool.clone(),
+        config.report_verification_hours,
+    ));
+    tokio::spawn(run_report_anonymiser(
+        app_state.pool.clone(),
+        config.report_retention_days,
+    ));
+    tokio::spawn(run_outbox_worker(
+        app_state.pool.clone(),
+        app_state.mailer.clone(),
+    ));
+    if let Some(digest) = config.digest.clone() {
+        tokio::spawn(run_digest_scheduler(
+            app_state.pool.clone(),
+            app_state.mailer.clone(),
+            app_state.report_settings.clone(),
+            digest,
+        ));
+    }
+    tokio::spawn(run_webhook_worker(
+        app_state.pool.clone(),
+        app_state.webhooks.clone(),
+    ));
 
     let app = router
         .with_state(app_state)
@@ -110,13 +229,41 @@ pub async fn run_server(config: Config) -> Result<()> {
     Ok(())
 }
 
+/// Rewrites every report still encrypted with a retired key, or stored
+/// before encryption was introduced, with the active key. Run it after
+/// adding a new key to the front of the keyring and before removing the old
+/// one.
+pub async fn reencrypt_reports(config: Config) -> Result<()> {
+    let (_router, app_state) = get_app(&config).await?;
+    let keyring = &app_state.report_settings.keyring;
+    let rewritten = Report::reencrypt(&app_state.pool, keyring).await?;
+    println!(
+        "Re-encrypted {} rows with key {}",
+        rewritten,
+        keyring.active_key_id()
+    );
+    Ok(())
+}
+
 pub fn get_test_config() -> Config {
     Config {
         db_url: "sqlite://:memory:".to_string(),
         frontend_origin: "http://localhost:1337".to_string(),
         admin_token: "MAGIC".to_string(),
-        sendgrid_api_key: "TEST".to_string(),
+        mail_transport: MailTransportConfig::Memory,
         email_from: "test@test.com".to_string(),
         email_name: "test test".to_string(),
+        email_templates_dir: None,
+        report_encryption_keys: Keyring::new(vec![("test".to_string(), [7; 32])]).unwrap(),
+        trash_retention_days: 30,
+        auto_hide_report_threshold: None,
+        report_recipients: HashMap::new(),
+        operator_report_emails: true,
+        digest: None,
+        counter_notice_waiting_days: 14,
+        report_verification_hours: 24,
+        report_retention_days: 90,
+        webhook_urls: Vec::new(),
+        webhook_secret: "test-secret".to_string(),
     }
 }
@@ -0,0 +1,203 @@
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::mailer::Mailer;
+use crate::models::{
+    counter_notice::CounterNotice,
+    digest::{DigestSettings, ModerationDigest},
+    now_millis,
+    outbox::OutboxEntry,
+    paste::Paste,
+    report::{Report, ReportSettings},
+    webhook::WebhookDelivery,
+    DAY_MILLIS, HOUR_MILLIS,
+};
+use crate::webhooks::Webhooks;
+
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const COUNTER_NOTICE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const REPORT_EXPIRY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const REPORT_RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const OUTBOX_INTERVAL: Duration = Duration::from_secs(15);
+const WEBHOOK_INTERVAL: Duration = Duration::from_secs(15);
+const DIGEST_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Purges every paste and report that has been in the trash for longer than
+/// `retention_days`.
+pub async fn purge_trash(pool: &SqlitePool, retention_days: i64) -> Result<(), Error> {
+    let deleted_before = now_millis() - retention_days * DAY_MILLIS;
+    let pastes = Paste::purge_trash(pool, deleted_before).await?;
+    let reports = Report::purge_trash(pool, deleted_before).await?;
+    if pastes + reports > 0 {
+        tracing::info!(
+            "Purged {} pastes and {} reports from trash",
+            pastes,
+            reports
+        );
+    }
+    Ok(())
+}
+
+pub async fn run_trash_purger(pool: SqlitePool, retention_days: i64) {
+    let mut interval = tokio::time::interval(TRASH_PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = purge_trash(&pool, retention_days).await {
+            tracing::error!("Failed to purge trash: {:?}", e);
+        }
+    }
+}
+
+/// Reinstates pastes whose counter-notice waiting period has elapsed without
+/// legal action being reported.
+pub async fn restore_counter_noticed(pool: &SqlitePool) -> Result<(), Error> {
+    let restored = CounterNotice::restore_due(pool, now_millis()).await?;
+    if restored > 0 {
+        tracing::info!("Restored {} pastes after counter-notice", restored);
+    }
+    Ok(())
+}
+
+pub async fn run_counter_notice_restorer(pool: SqlitePool) {
+    let mut interval = tokio::time::interval(COUNTER_NOTICE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = restore_counter_noticed(&pool).await {
+            tracing::error!("Failed to restore counter-noticed pastes: {:?}", e);
+        }
+    }
+}
+
+/// Deletes reports whose email address was not confirmed within
+/// `verification_ttl_hours`.
+pub async fn expire_unverified_reports(
+    pool: &SqlitePool,
+    verification_ttl_hours: i64,
+) -> Result<(), Error> {
+    let created_before = now_millis() - verification_ttl_hours * HOUR_MILLIS;
+    let expired = Report::expire_unverified(pool, created_before).await?;
+    if expired > 0 {
+        tracing::info!("Expired {} unconfirmed reports", expired);
+    }
+    Ok(())
+}
+
+pub async fn run_report_expirer(pool: SqlitePool, verification_ttl_hours: i64) {
+    let mut interval = tokio::time::interval(REPORT_EXPIRY_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = expire_unverified_reports(&pool, verification_ttl_hours).await {
+            tracing::error!("Failed to expire unconfirmed reports: {:?}", e);
+        }
+    }
+}
+
+/// Anonymises reports that have been closed for longer than
+/// `retention_days`, and drops dead-lettered emails and webhooks just as
+/// old.
+pub async fn anonymise_closed_reports(pool: &SqlitePool, retention_days: i64) -> Result<(), Error> {
+    let closed_before = now_millis() - retention_days * DAY_MILLIS;
+    let anonymised = Report::anonymise_closed(pool, closed_before).await?;
+    if anonymised > 0 {
+        tracing::info!("Anonymised {} closed reports", anonymised);
+    }
+    let purged = OutboxEntry::purge_dead(pool, closed_before).await?;
+    if purged > 0 {
+        tracing::info!("Purged {} dead-lettered emails", purged);
+    }
+    let purged = WebhookDelivery::purge_dead(pool, closed_before).await?;
+    if purged > 0 {
+        tracing::info!("Purged {} dead-lettered webhooks", purged);
+    }
+    Ok(())
+}
+
+pub async fn run_report_anonymiser(pool: SqlitePool, retention_days: i64) {
+    let mut interval = tokio::time::interval(REPORT_RETENTION_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = anonymise_closed_reports(&pool, retention_days).await {
+            tracing::error!("Failed to anonymise closed reports: {:?}", e);
+        }
+    }
+}
+
+/// Delivers queued emails that are due, retrying failed ones with backoff.
+pub async fn deliver_outbox(pool: &SqlitePool, mailer: &Mailer) -> Result<(), Error> {
+    let delivered = mailer.deliver_due(pool).await?;
+    if delivered > 0 {
+        tracing::info!("Delivered {} queued emails", delivered);
+    }
+    Ok(())
+}
+
+/// Sends new emails as soon as a request queues them, and retries failed
+/// ones every `OUTBOX_INTERVAL`.
+pub async fn run_outbox_worker(pool: SqlitePool, mailer: Mailer) {
+    let mut interval = tokio::time::interval(OUTBOX_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Err(e) = deliver_outbox(&pool, &mailer).await {
+                    tracing::error!("Failed to deliver queued emails: {:?}", e);
+                }
+            }
+            _ = mailer.notified() => mailer.flush(&pool).await,
+        }
+    }
+}
+
+/// Posts queued webhook events that are due, retrying failed ones with
+/// backoff.
+pub async fn deliver_webhooks(pool: &SqlitePool, webhooks: &Webhooks) -> Result<(), Error> {
+    let delivered = webhooks.deliver_due(pool).await?;
+    if delivered > 0 {
+        tracing::info!("Delivered {} queued webhooks", delivered);
+    }
+    Ok(())
+}
+
+/// Posts new events as soon as a request emits them, and retries failed
+/// ones every `WEBHOOK_INTERVAL`.
+pub async fn run_webhook_worker(pool: SqlitePool, webhooks: Webhooks) {
+    let mut interval = tokio::time::interval(WEBHOOK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Err(e) = deliver_webhooks(&pool, &webhooks).await {
+                    tracing::error!("Failed to deliver queued webhooks: {:?}", e);
+                }
+            }
+            _ = webhooks.notified() => webhooks.flush(&pool).await,
+        }
+    }
+}
+
+/// Sends the daily moderation digest once its time of day has come.
+pub async fn send_moderation_digest(
+    pool: &SqlitePool,
+    mailer: &Mailer,
+    settings: &ReportSettings,
+    digest: &DigestSettings,
+) -> Result<(), Error> {
+    if ModerationDigest::send_due(pool, mailer, settings, digest, now_millis()).await? {
+        tracing::info!("Sent the moderation digest");
+    }
+    Ok(())
+}
+
+pub async fn run_digest_scheduler(
+    pool: SqlitePool,
+    mailer: Mailer,
+    settings: ReportSettings,
+    digest: DigestSettings,
+) {
+    let mut interval = tokio::time::interval(DIGEST_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = send_moderation_digest(&pool, &mailer, &settings, &digest).await {
+            tracing::error!("Failed to send the moderation digest: {:?}", e);
+        }
+    }
+}
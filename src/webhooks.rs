@@ -0,0 +1,199 @@
+use anyhow::{bail, Context};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::{SqliteConnection, SqlitePool};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::error::Error;
+use crate::models::now_millis;
+use crate::models::report::ReportCategory;
+use crate::models::webhook::{QueuedWebhook, WebhookDelivery};
+
+/// Events delivered per claim.
+const DELIVERY_BATCH_SIZE: i64 = 50;
+/// How long an endpoint gets to answer. A slow endpoint holds up every
+/// event queued behind it.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const EVENT_HEADER: &str = "X-Anonpaste-Event";
+pub const DELIVERY_HEADER: &str = "X-Anonpaste-Delivery";
+pub const TIMESTAMP_HEADER: &str = "X-Anonpaste-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Anonpaste-Signature";
+
+/// Something that happened in moderation. Events carry ids rather than
+/// paste links, which hold decryption keys, or anything a reporter wrote.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", content = "data")]
+pub enum WebhookEvent {
+    /// A report reached the moderation queue, either confirmed by its
+    /// reporter or filed by a trusted flagger.
+    #[serde(rename = "report.created", rename_all = "camelCase")]
+    ReportCreated {
+        id: i64,
+        ticket_id: String,
+        category: ReportCategory,
+        priority: i64,
+        paste_ids: Vec<String>,
+    },
+    #[serde(rename = "paste.taken_down", rename_all = "camelCase")]
+    PasteTakenDown {
+        id: String,
+        reason: Option<String>,
+        report_id: Option<i64>,
+    },
+    /// A paste was moved to the trash.
+    #[serde(rename = "paste.deleted")]
+    PasteDeleted { id: String },
+}
+
+impl WebhookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::ReportCreated { .. } => "report.created",
+            WebhookEvent::PasteTakenDown { .. } => "paste.taken_down",
+            WebhookEvent::PasteDeleted { .. } => "paste.deleted",
+        }
+    }
+}
+
+/// The body posted to endpoints. `id` is the same for every endpoint and
+/// every attempt, so receivers can drop duplicates.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope<'a> {
+    id: &'a str,
+    created_at: i64,
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+}
+
+/// The signature sent with a delivery: the hex HMAC-SHA256 of
+/// `<timestamp>.<body>` keyed with the shared secret.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Posts moderation events to the configured endpoints through a queue,
+/// like the `Mailer` does with emails. Every delivery is signed and
+/// timestamped, so endpoints can check it came from us and is recent.
+#[derive(Clone)]
+pub struct Webhooks {
+    client: reqwest::Client,
+    urls: Arc<Vec<String>>,
+    secret: Arc<String>,
+    queued: Arc<Notify>,
+}
+
+impl Webhooks {
+    pub fn new(urls: Vec<String>, secret: String) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(TIMEOUT)
+            .build()
+            .context("cannot build the webhook client")?;
+        Ok(Webhooks {
+            client,
+            urls: Arc::new(urls),
+            secret: Arc::new(secret),
+            queued: Arc::new(Notify::new()),
+        })
+    }
+
+    /// Queues an event for every endpoint within the transaction that
+    /// caused it.
+    pub(crate) async fn emit(
+        &self,
+        conn: &mut SqliteConnection,
+        event: WebhookEvent,
+    ) -> Result<(), Error> {
+        if self.urls.is_empty() {
+            return Ok(());
+        }
+        let event_id = hex::encode(rand::random::<[u8; 16]>());
+        let payload = serde_json::to_string(&Envelope {
+            id: &event_id,
+            created_at: now_millis(),
+            event: &event,
+        })
+        .map_err(anyhow::Error::from)?;
+        for url in self.urls.iter() {
+            WebhookDelivery::enqueue(&mut *conn, &event_id, event.name(), url, &payload).await?;
+        }
+        Ok(())
+    }
+
+    async fn post(&self, webhook: &QueuedWebhook) -> anyhow::Result<()> {
+        let timestamp = now_millis() / 1000;
+        let response = self
+            .client
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, &webhook.event)
+            .header(DELIVERY_HEADER, &webhook.event_id)
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(
+                SIGNATURE_HEADER,
+                sign(&self.secret, timestamp, &webhook.payload),
+            )
+            .body(webhook.payload.clone())
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            bail!("endpoint answered {}", response.status());
+        }
+        Ok(())
+    }
+
+    async fn deliver(&self, pool: &SqlitePool, only_new: bool) -> Result<u64, Error> {
+        let mut delivered = 0;
+        loop {
+            let webhooks = WebhookDelivery::claim(pool, only_new, DELIVERY_BATCH_SIZE).await?;
+            if webhooks.is_empty() {
+                return Ok(delivered);
+            }
+            for webhook in webhooks {
+                match self.post(&webhook).await {
+                    Ok(()) => {
+                        WebhookDelivery::delivered(pool, webhook.id).await?;
+                        delivered += 1;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to deliver webhook {}: {:?}", webhook.id, e);
+                        WebhookDelivery::failed(pool, &webhook, &format!("{:#}", e)).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Delivers every queued event that is due, including retries. Returns
+    /// the number delivered.
+    pub async fn deliver_due(&self, pool: &SqlitePool) -> Result<u64, Error> {
+        self.deliver(pool, false).await
+    }
+
+    /// Wakes the webhook worker once a transaction that emitted events has
+    /// committed.
+    pub fn notify(&self) {
+        self.queued.notify_one();
+    }
+
+    /// Waits for the next `notify`.
+    pub async fn notified(&self) {
+        self.queued.notified().await;
+    }
+
+    /// Delivers events that have not been tried yet. Failures are left to be
+    /// retried once they are due.
+    pub async fn flush(&self, pool: &SqlitePool) {
+        if let Err(e) = self.deliver(pool, true).await {
+            tracing::error!("Failed to flush webhooks: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use anonpaste::{
+    models::paste::{CreatePaste, Paste},
+    server::{get_app, get_test_config},
+};
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+};
+use common::assert_admin_only;
+use serde_json::Value;
+use tower::ServiceExt;
+
+mod common;
+
+#[tokio::test]
+async fn delete_paste_is_audited() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let app = router.with_state(app_state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/api/paste/test-id")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .header("x-actor", "alice")
+                .header("x-audit-reason", "DMCA notice")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/audit")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let entry = &body["items"][0];
+    assert_eq!(entry["actor"], "alice");
+    assert_eq!(entry["action"], "paste.delete");
+    assert_eq!(entry["targetId"], "test-id");
+    assert_eq!(entry["reason"], "DMCA notice");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/audit/verify")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["valid"], true);
+    assert_eq!(body["checked"], 1);
+}
+
+#[tokio::test]
+async fn tampered_audit_log_fails_verification() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    for id in ["first", "second"] {
+        Paste::create(
+            &app_state.pool,
+            CreatePaste {
+                id: id.to_string(),
+                content: "Hello".to_string(),
+                expiry_time: None,
+                expiry_views: None,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let app = router.with_state(app_state.clone());
+    for id in ["first", "second"] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri(format!("/api/paste/{}", id))
+                    .header("Authorization", format!("Bearer {}", &config.admin_token))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    assert!(
+        sqlx::query("UPDATE audit_log SET actor = 'mallory' WHERE target_id = 'first'")
+            .execute(&app_state.pool)
+            .await
+            .is_err()
+    );
+    sqlx::query("DROP TRIGGER audit_log_no_update")
+        .execute(&app_state.pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE audit_log SET actor = 'mallory' WHERE target_id = 'first'")
+        .execute(&app_state.pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/audit/verify")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["valid"], false);
+    assert_eq!(body["checked"], 0);
+}
+
+#[tokio::test]
+async fn audit_routes_require_admin_token() {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    assert_admin_only(
+        &router.with_state(app_state),
+        &[("GET", "/api/audit"), ("GET", "/api/audit/verify")],
+    )
+    .await;
+}
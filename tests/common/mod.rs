@@ -0,0 +1,72 @@
+#![allow(dead_code)]
+
+use anonpaste::{mailer::ReportMessage, models::audit::AuditContext, server::AppState};
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    Router,
+};
+use serde_json::Value;
+use tower::ServiceExt;
+
+pub fn admin() -> AuditContext {
+    AuditContext {
+        actor: "admin".to_string(),
+        reason: None,
+    }
+}
+
+/// Delivers the emails queued so far, as the outbox worker would once woken,
+/// and returns everything sent.
+pub async fn sent_emails(app_state: &AppState) -> Vec<ReportMessage> {
+    app_state.mailer.flush(&app_state.pool).await;
+    app_state.mailer.get_sent_emails()
+}
+
+/// Sends a JSON request as a client behind the proxy would, so the rate
+/// limiter can tell who it comes from. Bodies that are not JSON come back
+/// as `null`.
+pub async fn send_json(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    token: Option<&str>,
+    body: Value,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("x-real-ip", "127.0.0.1")
+        .header("content-type", "application/json");
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+pub async fn post_json(
+    app: &Router,
+    uri: &str,
+    token: Option<&str>,
+    body: Value,
+) -> (StatusCode, Value) {
+    send_json(app, "POST", uri, token, body).await
+}
+
+/// Checks that each of `routes` turns away requests without the admin token
+/// or with the wrong one.
+pub async fn assert_admin_only(app: &Router, routes: &[(&str, &str)]) {
+    for &(method, uri) in routes {
+        for token in [None, Some("not-the-admin-token")] {
+            let (status, _) = send_json(app, method, uri, token, Value::Null).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+        }
+    }
+}
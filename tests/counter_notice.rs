@@ -0,0 +1,260 @@
+use anonpaste::{
+    models::audit::{AuditEntry, ListAudit},
+    models::counter_notice::{CounterNotice, CreateCounterNotice, SetLegalAction},
+    models::paste::{CreatePaste, Paste, PasteStatus, UpdatePasteStatus},
+    models::report::{CreateReport, Report, ReportCategory},
+    server::{get_app, get_test_config, AppState},
+};
+use axum::{http::StatusCode, Router};
+use common::{admin, assert_admin_only, post_json, sent_emails};
+use serde_json::{json, Value};
+
+mod common;
+
+fn counter_notice() -> Value {
+    json!({
+        "name": "Jane Uploader",
+        "email": "jane@uploader.com",
+        "address": "1 Main St, Springfield",
+        "statement": "I swear under penalty of perjury that the content was removed by mistake.",
+        "consentToJurisdiction": true,
+        "signature": "Jane Uploader"
+    })
+}
+
+/// Files and confirms a report against the test paste, then takes the paste
+/// down on its behalf.
+async fn take_down(app_state: &AppState, report_id: i64) {
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Please remove it".to_string(),
+            email: "reporter@leaksdown.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: Vec::new(),
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    let confirmation = sent_emails(app_state).await.pop().unwrap();
+    let token = confirmation
+        .content
+        .split("/report/verify/")
+        .nth(1)
+        .unwrap();
+    Report::verify(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.webhooks,
+        &app_state.report_settings,
+        token.split_whitespace().next().unwrap().to_string(),
+    )
+    .await
+    .unwrap();
+    Paste::set_status(
+        &app_state.pool,
+        &app_state.webhooks,
+        "test-id".to_string(),
+        UpdatePasteStatus {
+            status: PasteStatus::TakenDown,
+            reason: Some("DMCA".to_string()),
+            report_id: Some(report_id),
+        },
+        &admin(),
+    )
+    .await
+    .unwrap();
+}
+
+async fn taken_down_paste() -> (Router, AppState) {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+    take_down(&app_state, 1).await;
+    (router.with_state(app_state.clone()), app_state)
+}
+
+async fn file_counter_notice(app: &Router, body: Value) -> (StatusCode, Value) {
+    post_json(app, "/api/paste/test-id/counter-notice", None, body).await
+}
+
+#[tokio::test]
+async fn counter_notice_restores_paste_after_waiting_period() {
+    let (app, app_state) = taken_down_paste().await;
+
+    let (status, notice) = file_counter_notice(&app, counter_notice()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(notice["reportId"], 1);
+    assert_eq!(
+        notice["restoreAfter"].as_i64().unwrap() - notice["createdAt"].as_i64().unwrap(),
+        14 * 24 * 60 * 60 * 1000
+    );
+
+    let forwarded = sent_emails(&app_state).await.pop().unwrap();
+    assert_eq!(forwarded.to, "reporter@leaksdown.com");
+    assert_eq!(forwarded.subject, "DMCA Counter-Notice Received");
+    assert!(forwarded.content.contains("Jane Uploader"));
+
+    let restore_after = notice["restoreAfter"].as_i64().unwrap();
+    assert_eq!(
+        CounterNotice::restore_due(&app_state.pool, restore_after - 1)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        CounterNotice::restore_due(&app_state.pool, restore_after)
+            .await
+            .unwrap(),
+        1
+    );
+    let paste = Paste::inspect(&app_state.pool, "test-id".to_string())
+        .await
+        .unwrap();
+    assert_eq!(paste.status, PasteStatus::Active);
+
+    let case = Report::get(&app_state.pool, &app_state.report_settings.keyring, 1)
+        .await
+        .unwrap();
+    assert_eq!(case.counter_notices.len(), 1);
+    assert!(case.counter_notices[0].restored_at.is_some());
+}
+
+#[tokio::test]
+async fn pending_legal_action_blocks_restore() {
+    let (app, app_state) = taken_down_paste().await;
+    let (status, notice) = file_counter_notice(&app, counter_notice()).await;
+    assert_eq!(status, StatusCode::OK);
+    let id = notice["id"].as_i64().unwrap();
+
+    CounterNotice::set_legal_action(
+        &app_state.pool,
+        id,
+        SetLegalAction { pending: true },
+        &admin(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        CounterNotice::restore_due(&app_state.pool, i64::MAX)
+            .await
+            .unwrap(),
+        0
+    );
+    let paste = Paste::inspect(&app_state.pool, "test-id".to_string())
+        .await
+        .unwrap();
+    assert_eq!(paste.status, PasteStatus::TakenDown);
+}
+
+#[tokio::test]
+async fn counter_notice_requires_statement_and_takedown() {
+    let (app, app_state) = taken_down_paste().await;
+    let mut body = counter_notice();
+    body["consentToJurisdiction"] = json!(false);
+    body["signature"] = json!(" ");
+    let (status, errors) = file_counter_notice(&app, body).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        errors["errors"],
+        json!([
+            {"field": "signature", "msg": "REQUIRED"},
+            {"field": "consentToJurisdiction", "msg": "CONSENT_REQUIRED"}
+        ])
+    );
+
+    Paste::set_status(
+        &app_state.pool,
+        &app_state.webhooks,
+        "test-id".to_string(),
+        UpdatePasteStatus {
+            status: PasteStatus::Active,
+            reason: None,
+            report_id: None,
+        },
+        &admin(),
+    )
+    .await
+    .unwrap();
+    let (status, _) = file_counter_notice(&app, counter_notice()).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn one_pending_counter_notice_per_takedown() {
+    let (app, app_state) = taken_down_paste().await;
+    let (status, first) = file_counter_notice(&app, counter_notice()).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = file_counter_notice(&app, counter_notice()).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // A takedown under a new report can be contested again. The public
+    // rate limit is spent by now, so this one skips the HTTP layer.
+    take_down(&app_state, 2).await;
+    let second = CounterNotice::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        "test-id".to_string(),
+        CreateCounterNotice {
+            name: "Jane Uploader".to_string(),
+            email: "jane@uploader.com".to_string(),
+            address: "1 Main St, Springfield".to_string(),
+            statement: "The content was removed by mistake.".to_string(),
+            consent_to_jurisdiction: true,
+            signature: "Jane Uploader".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(second.report_id, 2);
+
+    let audit = AuditEntry::list(
+        &app_state.pool,
+        ListAudit {
+            cursor: None,
+            limit: None,
+        },
+    )
+    .await
+    .unwrap();
+    let filed: Vec<(&str, &str)> = audit
+        .items
+        .iter()
+        .filter(|entry| entry.action == "counter_notice.create")
+        .map(|entry| (entry.actor.as_str(), entry.target_id.as_str()))
+        .collect();
+    assert_eq!(
+        filed,
+        vec![
+            ("paste_owner", first["id"].to_string().as_str()),
+            ("paste_owner", second.id.to_string().as_str()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn counter_notice_admin_routes_require_admin_token() {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    assert_admin_only(
+        &router.with_state(app_state),
+        &[
+            ("GET", "/api/counter-notice"),
+            ("PUT", "/api/counter-notice/1/legal-action"),
+        ],
+    )
+    .await;
+}
@@ -0,0 +1,138 @@
+use anonpaste::{
+    models::digest::{DigestSettings, ModerationDigest},
+    models::report::{CreateReport, Report, ReportCategory},
+    models::HOUR_MILLIS,
+    server::{get_app, get_test_config, AppState},
+};
+use chrono::NaiveTime;
+use common::sent_emails;
+use std::collections::HashMap;
+
+mod common;
+
+/// 2026-10-19 08:00 in Berlin.
+const DIGEST_AT: i64 = 1_792_389_600_000;
+
+/// Files a report and confirms it, as if `hours_ago` hours before the
+/// digest.
+async fn confirmed_report(app_state: &AppState, category: ReportCategory, hours_ago: f64) {
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Please remove it".to_string(),
+            email: "reporter@example.com".to_string(),
+            category,
+            attachments: Vec::new(),
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    let confirmation = sent_emails(app_state).await.pop().unwrap();
+    let token = confirmation
+        .content
+        .split("/report/verify/")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap();
+    Report::verify(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.webhooks,
+        &app_state.report_settings,
+        token.to_string(),
+    )
+    .await
+    .unwrap();
+    let verified_at = DIGEST_AT - (hours_ago * HOUR_MILLIS as f64) as i64;
+    sqlx::query("UPDATE report SET verified_at = ? WHERE id = (SELECT MAX(id) FROM report)")
+        .bind(verified_at)
+        .execute(&app_state.pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn operators_get_a_daily_digest_instead_of_report_emails() {
+    let mut config = get_test_config();
+    config.report_recipients =
+        HashMap::from([(ReportCategory::Csam, vec!["csam@ops.test".to_string()])]);
+    config.operator_report_emails = false;
+    let digest = DigestSettings {
+        time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+        timezone: chrono_tz::Europe::Berlin,
+        report_url: "https://admin.test/reports/{id}".to_string(),
+    };
+    let (_router, app_state) = get_app(&config).await.unwrap();
+
+    confirmed_report(&app_state, ReportCategory::Dmca, 0.5).await;
+    confirmed_report(&app_state, ReportCategory::Csam, 2.0).await;
+    confirmed_report(&app_state, ReportCategory::Dmca, 2.0).await;
+    // Only reporters hear about confirmed reports.
+    assert!(sent_emails(&app_state)
+        .await
+        .iter()
+        .all(|message| message.to == "reporter@example.com"));
+
+    // Yesterday's digest went out late, after the third report came in.
+    sqlx::query("INSERT INTO moderation_digest ( date, sent_at ) VALUES ( '2026-10-18', ? )")
+        .bind(DIGEST_AT - HOUR_MILLIS)
+        .execute(&app_state.pool)
+        .await
+        .unwrap();
+
+    let send = |now: i64| {
+        ModerationDigest::send_due(
+            &app_state.pool,
+            &app_state.mailer,
+            &app_state.report_settings,
+            &digest,
+            now,
+        )
+    };
+    assert!(!send(DIGEST_AT - 60 * 1000).await.unwrap());
+    assert!(send(DIGEST_AT).await.unwrap());
+    assert!(!send(DIGEST_AT + HOUR_MILLIS).await.unwrap());
+
+    let digests: Vec<_> = sent_emails(&app_state)
+        .await
+        .into_iter()
+        .filter(|message| message.to != "reporter@example.com")
+        .collect();
+    assert_eq!(digests.len(), 2);
+    for message in &digests {
+        assert_eq!(message.subject, "Moderation digest for 2026-10-19");
+    }
+
+    let csam = digests
+        .iter()
+        .find(|message| message.to == "csam@ops.test")
+        .unwrap();
+    assert!(csam.content.contains("CSAM Report\n\nOverdue (1):\n"));
+    assert!(csam
+        .content
+        .contains("(open): https://admin.test/reports/2\n"));
+    assert!(!csam.content.contains("DMCA"));
+    assert!(csam
+        .html
+        .contains("<a href=\"https:&#x2f;&#x2f;admin.test&#x2f;reports&#x2f;2\">"));
+
+    let others = digests
+        .iter()
+        .find(|message| message.to == config.email_from)
+        .unwrap();
+    assert!(others.content.contains("DMCA Report\n"));
+    assert!(others.content.contains("New since the last digest (1):\n"));
+    assert!(others.content.contains("https://admin.test/reports/1\n"));
+    assert!(others.content.contains("Still open (1):\n"));
+    assert!(others.content.contains("https://admin.test/reports/3\n"));
+    assert!(!others.content.contains("CSAM"));
+
+    // The next day's digest still lists what is left, now overdue.
+    assert!(send(DIGEST_AT + 24 * HOUR_MILLIS).await.unwrap());
+    let next = sent_emails(&app_state).await.pop().unwrap();
+    assert_eq!(next.subject, "Moderation digest for 2026-10-20");
+}
@@ -0,0 +1,230 @@
+use anonpaste::{
+    models::audit::{AuditEntry, ListAudit},
+    models::flagger::TrustedFlagger,
+    models::paste::{CreatePaste, Paste, PasteStatus},
+    models::report::{Report, ReportStatus, TransitionReport},
+    server::{get_app, get_test_config, AppState},
+};
+use axum::{http::StatusCode, Router};
+use common::{admin, assert_admin_only, post_json, send_json, sent_emails};
+use serde_json::{json, Value};
+
+mod common;
+
+async fn app_with_paste() -> (Router, AppState) {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+    (router.with_state(app_state.clone()), app_state)
+}
+
+fn flagged_report() -> Value {
+    json!({
+        "links": ["http://localhost:1337/test-id#magic-key"],
+        "message": "Phishing kit",
+        "category": "phishing"
+    })
+}
+
+#[tokio::test]
+async fn trusted_flagger_quarantines_until_review() {
+    let (app, app_state) = app_with_paste().await;
+    let (status, issued) = send_json(
+        &app,
+        "POST",
+        "/api/flagger",
+        Some("MAGIC"),
+        json!({"name": "Example CERT", "email": "abuse@cert.example", "immediateQuarantine": true}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(issued["immediateQuarantine"], true);
+    let api_key = issued["apiKey"].as_str().unwrap();
+
+    let (status, report) = send_json(
+        &app,
+        "POST",
+        "/api/flagger/reports",
+        Some(api_key),
+        flagged_report(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["quarantined"], json!(["test-id"]));
+
+    // No confirmation round-trip: the report is in the queue right away.
+    let id = report["id"].as_i64().unwrap();
+    let case = Report::get(&app_state.pool, &app_state.report_settings.keyring, id)
+        .await
+        .unwrap();
+    assert_eq!(case.trusted_flagger_id, issued["id"].as_i64());
+    assert_eq!(case.email, "abuse@cert.example");
+    let sent = sent_emails(&app_state).await;
+    assert_eq!(sent[0].subject, "Phishing Report Initiated");
+
+    let paste = Paste::inspect(&app_state.pool, "test-id".to_string())
+        .await
+        .unwrap();
+    assert_eq!(paste.status, PasteStatus::Quarantined);
+    assert_eq!(paste.status_report_id, Some(id));
+
+    Report::transition(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings.keyring,
+        id,
+        TransitionReport {
+            status: ReportStatus::Rejected,
+        },
+        &admin(),
+    )
+    .await
+    .unwrap();
+    let paste = Paste::inspect(&app_state.pool, "test-id".to_string())
+        .await
+        .unwrap();
+    assert_eq!(paste.status, PasteStatus::Active);
+
+    let audit = AuditEntry::list(
+        &app_state.pool,
+        ListAudit {
+            cursor: None,
+            limit: None,
+        },
+    )
+    .await
+    .unwrap();
+    let actions: Vec<(&str, &str)> = audit
+        .items
+        .iter()
+        .map(|entry| (entry.actor.as_str(), entry.action.as_str()))
+        .collect();
+    assert_eq!(
+        actions,
+        vec![
+            ("admin", "flagger.issue"),
+            ("flagger:1", "report.create"),
+            ("flagger:1", "paste.quarantine"),
+            ("admin", "report.rejected"),
+            ("admin", "paste.reinstate"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn revoked_flagger_key_is_rejected() {
+    let (app, app_state) = app_with_paste().await;
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/flagger/reports",
+        Some("not-a-key"),
+        flagged_report(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (_, issued) = send_json(
+        &app,
+        "POST",
+        "/api/flagger",
+        Some("MAGIC"),
+        json!({"name": "Rights Agent", "email": "notices@agent.example"}),
+    )
+    .await;
+    assert_eq!(issued["immediateQuarantine"], false);
+    let api_key = issued["apiKey"].as_str().unwrap();
+    let (status, report) = send_json(
+        &app,
+        "POST",
+        "/api/flagger/reports",
+        Some(api_key),
+        flagged_report(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["quarantined"], json!([]));
+
+    TrustedFlagger::revoke(&app_state.pool, issued["id"].as_i64().unwrap(), &admin())
+        .await
+        .unwrap();
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/api/flagger/reports",
+        Some(api_key),
+        flagged_report(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (_, flaggers) = send_json(&app, "GET", "/api/flagger", Some("MAGIC"), Value::Null).await;
+    assert_eq!(flaggers, json!([]));
+}
+
+#[tokio::test]
+async fn flagger_rate_limit_follows_api_key() {
+    let (app, _app_state) = app_with_paste().await;
+    let (_, issued) = send_json(
+        &app,
+        "POST",
+        "/api/flagger",
+        Some("MAGIC"),
+        json!({"name": "Example CERT", "email": "abuse@cert.example"}),
+    )
+    .await;
+    let api_key = issued["apiKey"].as_str().unwrap();
+
+    // Guessing keys runs into the public limit...
+    for expected in [
+        StatusCode::UNAUTHORIZED,
+        StatusCode::UNAUTHORIZED,
+        StatusCode::TOO_MANY_REQUESTS,
+    ] {
+        let (status, _) = post_json(
+            &app,
+            "/api/flagger/reports",
+            Some("not-a-key"),
+            flagged_report(),
+        )
+        .await;
+        assert_eq!(status, expected);
+    }
+    // ...which does not hold back a flagger reporting from the same address.
+    for _ in 0..5 {
+        let (status, _) = post_json(
+            &app,
+            "/api/flagger/reports",
+            Some(api_key),
+            flagged_report(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn issuing_keys_requires_admin_token() {
+    let (app, _app_state) = app_with_paste().await;
+    let flagger = json!({"name": "Example CERT", "email": "abuse@cert.example"});
+    let (status, _) = post_json(&app, "/api/flagger", None, flagger.clone()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = post_json(&app, "/api/flagger", Some("not-a-key"), flagger).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_admin_only(
+        &app,
+        &[("GET", "/api/flagger"), ("DELETE", "/api/flagger/1")],
+    )
+    .await;
+
+    let (_, flaggers) = send_json(&app, "GET", "/api/flagger", Some("MAGIC"), Value::Null).await;
+    assert_eq!(flaggers, json!([]));
+}
@@ -0,0 +1,191 @@
+use anonpaste::{
+    models::hold::{CreateLegalHold, HoldTarget, LegalHold},
+    models::paste::{CreatePaste, Paste},
+    server::{get_app, get_test_config},
+};
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+};
+use common::{admin, assert_admin_only, send_json};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+mod common;
+
+#[tokio::test]
+async fn held_paste_survives_purge() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let response = router
+        .with_state(app_state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/hold")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"targetType": "paste", "targetId": "test-id", "caseReference": "CASE-42"})
+                        .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let hold: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(hold["caseReference"], "CASE-42");
+
+    Paste::delete(
+        &app_state.pool,
+        &app_state.webhooks,
+        "test-id".to_string(),
+        &admin(),
+    )
+    .await
+    .unwrap();
+    assert!(Paste::view(&app_state.pool, "test-id".to_string())
+        .await
+        .is_err());
+    assert_eq!(
+        Paste::purge_trash(&app_state.pool, i64::MAX).await.unwrap(),
+        0
+    );
+
+    LegalHold::lift(&app_state.pool, hold["id"].as_i64().unwrap(), &admin())
+        .await
+        .unwrap();
+    assert_eq!(
+        Paste::purge_trash(&app_state.pool, i64::MAX).await.unwrap(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn hold_requires_existing_target() {
+    let (_router, app_state) = get_app(&get_test_config()).await.unwrap();
+    let result = LegalHold::create(
+        &app_state.pool,
+        CreateLegalHold {
+            target_type: HoldTarget::Report,
+            target_id: "1".to_string(),
+            case_reference: "CASE-42".to_string(),
+            ends_at: None,
+        },
+        &admin(),
+    )
+    .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn held_paste_cannot_be_deleted_directly() {
+    let (_router, app_state) = get_app(&get_test_config()).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+    LegalHold::create(
+        &app_state.pool,
+        CreateLegalHold {
+            target_type: HoldTarget::Paste,
+            target_id: "test-id".to_string(),
+            case_reference: "CASE-42".to_string(),
+            ends_at: None,
+        },
+        &admin(),
+    )
+    .await
+    .unwrap();
+
+    assert!(sqlx::query("DELETE FROM paste WHERE id = 'test-id'")
+        .execute(&app_state.pool)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn held_paste_cannot_be_rewritten() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    let app = router.with_state(app_state.clone());
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+    LegalHold::create(
+        &app_state.pool,
+        CreateLegalHold {
+            target_type: HoldTarget::Paste,
+            target_id: "test-id".to_string(),
+            case_reference: "CASE-42".to_string(),
+            ends_at: None,
+        },
+        &admin(),
+    )
+    .await
+    .unwrap();
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        "/api/paste/test-id",
+        Some(&config.admin_token),
+        json!({"content": "Nothing to see here", "expiryTime": null, "expiryViews": null}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["msg"], "UNDER_LEGAL_HOLD");
+    assert!(
+        sqlx::query("UPDATE paste SET content = 'Nothing to see here' WHERE id = 'test-id'")
+            .execute(&app_state.pool)
+            .await
+            .is_err()
+    );
+    let paste = Paste::inspect(&app_state.pool, "test-id".to_string())
+        .await
+        .unwrap();
+    assert_eq!(paste.content, "Hello");
+}
+
+#[tokio::test]
+async fn hold_routes_require_admin_token() {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    assert_admin_only(
+        &router.with_state(app_state),
+        &[
+            ("GET", "/api/hold"),
+            ("POST", "/api/hold"),
+            ("DELETE", "/api/hold/1"),
+        ],
+    )
+    .await;
+}
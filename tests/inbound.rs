@@ -0,0 +1,196 @@
+use anonpaste::{
+    models::report::{CreateReport, ListReports, Report, ReportCategory, ReportStatus},
+    server::{get_app, get_test_config, AppState},
+};
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::{assert_admin_only, sent_emails};
+use tower::ServiceExt;
+
+mod common;
+
+/// Follows the confirmation link in the most recent email.
+async fn confirm_last_report(app_state: &AppState) {
+    let confirmation = sent_emails(app_state).await.pop().unwrap();
+    let token = confirmation
+        .content
+        .split("/report/verify/")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap();
+    Report::verify(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.webhooks,
+        &app_state.report_settings,
+        token.to_string(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn raw_email_creates_report() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    let raw = "From: DMCA Agent <agent@takedowns.com>\r\n\
+        To: abuse@test.com\r\n\
+        Subject: DMCA Notice\r\n\
+        Content-Type: text/plain; charset=utf-8\r\n\
+        \r\n\
+        The following paste infringes my client's copyright:\r\n\
+        <http://localhost:1337/test-id#magic-key>\r\n\
+        https://elsewhere.com/other-id\r\n";
+
+    let response = router
+        .with_state(app_state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/inbound-email")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .header("content-type", "message/rfc822")
+                .body(Body::from(raw))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let confirmation = sent_emails(&app_state).await.pop().unwrap();
+    assert_eq!(confirmation.to, "agent@takedowns.com");
+    assert_eq!(confirmation.subject, "Confirm your DMCA Report");
+
+    confirm_last_report(&app_state).await;
+    let reports = Report::list(
+        &app_state.pool,
+        &app_state.report_settings.keyring,
+        ListReports::default(),
+    )
+    .await
+    .unwrap();
+    let report = reports.items.first().unwrap();
+    assert_eq!(
+        report.links,
+        vec!["http://localhost:1337/test-id#magic-key".to_string()]
+    );
+    assert_eq!(report.email, "agent@takedowns.com");
+    assert_eq!(report.status, ReportStatus::Open);
+}
+
+#[tokio::test]
+async fn reply_with_ticket_id_is_appended() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Please remove it".to_string(),
+            email: "federico@leaksdown.apiplant.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: Vec::new(),
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    confirm_last_report(&app_state).await;
+    let ticket_id = Report::get(&app_state.pool, &app_state.report_settings.keyring, 1)
+        .await
+        .unwrap()
+        .ticket_id;
+
+    let boundary = "XYZ";
+    let fields = [
+        (
+            "from",
+            "Federico <federico@leaksdown.apiplant.com>".to_string(),
+        ),
+        ("subject", format!("Re: DMCA Report [{}]", ticket_id)),
+        ("text", "The paste is still online.".to_string()),
+    ];
+    let body: String = fields
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            )
+        })
+        .chain([format!("--{}--\r\n", boundary)])
+        .collect();
+
+    let response = router
+        .with_state(app_state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/inbound-email")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .header(
+                    "content-type",
+                    format!("multipart/form-data; boundary={}", boundary),
+                )
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let case = Report::get(&app_state.pool, &app_state.report_settings.keyring, 1)
+        .await
+        .unwrap();
+    assert_eq!(case.messages.len(), 1);
+    assert_eq!(case.messages[0].author, "federico@leaksdown.apiplant.com");
+    assert_eq!(case.messages[0].body, "The paste is still online.");
+    assert_eq!(
+        Report::list(
+            &app_state.pool,
+            &app_state.report_settings.keyring,
+            ListReports::default()
+        )
+        .await
+        .unwrap()
+        .items
+        .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn email_without_links_is_rejected() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    let raw = "From: someone@example.com\r\nSubject: Hello\r\n\r\nJust saying hi.\r\n";
+
+    let response = router
+        .with_state(app_state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/inbound-email")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::from(raw))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(sent_emails(&app_state).await.is_empty());
+}
+
+#[tokio::test]
+async fn inbound_email_requires_admin_token() {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    assert_admin_only(
+        &router.with_state(app_state),
+        &[("POST", "/api/inbound-email")],
+    )
+    .await;
+}
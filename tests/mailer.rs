@@ -0,0 +1,272 @@
+use anonpaste::{
+    mailer::{templates::Templates, transport::MailTransportConfig, Mailer},
+    models::report::ReportCategory,
+    server::{get_app, get_test_config},
+};
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+};
+use common::assert_admin_only;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tower::ServiceExt;
+
+mod common;
+
+async fn mailer(transport: MailTransportConfig, templates: Templates) -> (Mailer, SqlitePool) {
+    let config = get_test_config();
+    let (_, app_state) = get_app(&config).await.unwrap();
+    let mailer = Mailer::new(
+        transport.build().unwrap(),
+        templates,
+        config.report_encryption_keys,
+        "info@anonpaste.test".to_string(),
+        "AnonPaste".to_string(),
+    );
+    (mailer, app_state.pool)
+}
+
+fn temp_dir(prefix: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "anonpaste-{}-{}",
+        prefix,
+        hex::encode(rand::random::<[u8; 8]>())
+    ))
+}
+
+async fn confirm(mailer: &Mailer, pool: &SqlitePool, link: &str) {
+    let mut conn = pool.acquire().await.unwrap();
+    mailer
+        .confirm_report(
+            &mut conn,
+            "reporter@example.com",
+            "en",
+            "TICKET-1",
+            &[link.to_string()],
+            ReportCategory::Dmca,
+            "http://localhost:1234/report/verify/token",
+        )
+        .await
+        .unwrap();
+    drop(conn);
+    mailer.flush(pool).await;
+}
+
+/// Serves SMTP on every connection the client opens and sends the envelope
+/// recipients and data of each message it receives down `messages`.
+async fn smtp_sink(listener: TcpListener, messages: UnboundedSender<(Vec<String>, String)>) {
+    loop {
+        let (stream, _) = listener.accept().await.unwrap();
+        tokio::spawn(smtp_session(stream, messages.clone()));
+    }
+}
+
+async fn smtp_session(stream: TcpStream, messages: UnboundedSender<(Vec<String>, String)>) {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    let mut recipients = Vec::new();
+    write.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let command = line.to_ascii_uppercase();
+        let reply: &[u8] = if command.starts_with("EHLO") || command.starts_with("HELO") {
+            b"250 sink\r\n"
+        } else if command.starts_with("RCPT TO:") {
+            recipients.push(line[8..].trim().to_string());
+            b"250 OK\r\n"
+        } else if command.starts_with("DATA") {
+            write.write_all(b"354 Go ahead\r\n").await.unwrap();
+            let mut data = String::new();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line == "." {
+                    break;
+                }
+                data.push_str(&line);
+                data.push('\n');
+            }
+            let _ = messages.send((std::mem::take(&mut recipients), data));
+            b"250 Queued\r\n"
+        } else if command.starts_with("RSET") {
+            recipients.clear();
+            b"250 OK\r\n"
+        } else if command.starts_with("QUIT") {
+            let _ = write.write_all(b"221 Bye\r\n").await;
+            return;
+        } else {
+            // MAIL FROM and NOOP.
+            b"250 OK\r\n"
+        };
+        if write.write_all(reply).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[tokio::test]
+async fn smtp_transport_delivers_to_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (messages, mut received) = unbounded_channel();
+    let sink = tokio::spawn(smtp_sink(listener, messages));
+
+    let (mailer, pool) = mailer(
+        MailTransportConfig::Smtp {
+            url: format!("smtp://127.0.0.1:{}", port),
+        },
+        Templates::load(None).unwrap(),
+    )
+    .await;
+    let (recipients, data) = tokio::time::timeout(Duration::from_secs(10), async {
+        confirm(&mailer, &pool, "http://localhost:1337/test-id").await;
+        received.recv().await.unwrap()
+    })
+    .await
+    .expect("the SMTP server received no message");
+    sink.abort();
+    // Nothing is captured outside of the in-memory transport.
+    assert!(mailer.get_sent_emails().is_empty());
+    assert_eq!(recipients, vec!["<reporter@example.com>"]);
+    assert!(data.contains("Subject: Confirm your DMCA Report"));
+    assert!(data.contains("From: AnonPaste <info@anonpaste.test>"));
+    assert!(data.contains("Content-Type: multipart/alternative"));
+    assert!(data.contains("Content-Type: text/html"));
+    assert!(data.contains("TICKET-1"));
+}
+
+#[tokio::test]
+async fn maildir_transport_writes_messages() {
+    let path = temp_dir("maildir");
+    let (mailer, pool) = mailer(
+        MailTransportConfig::Maildir { path: path.clone() },
+        Templates::load(None).unwrap(),
+    )
+    .await;
+    confirm(&mailer, &pool, "http://localhost:1337/test-id").await;
+
+    let files: Vec<_> = std::fs::read_dir(path.join("new"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 1);
+    let message = std::fs::read_to_string(&files[0]).unwrap();
+    assert!(message.contains("To: reporter@example.com"));
+    assert!(message.contains("Subject: Confirm your DMCA Report"));
+    assert_eq!(std::fs::read_dir(path.join("tmp")).unwrap().count(), 0);
+    std::fs::remove_dir_all(path).unwrap();
+}
+
+#[tokio::test]
+async fn templates_are_overridden_from_directory() {
+    let dir = temp_dir("templates");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("confirm_report.subject"),
+        "[{{ instance_name }}] Please confirm {{ ticket_id }}",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("confirm_report.html"),
+        "<p>{% for link in links %}{{ link }}{% endfor %}</p>",
+    )
+    .unwrap();
+    let (mailer, pool) = mailer(
+        MailTransportConfig::Memory,
+        Templates::load(Some(&dir)).unwrap(),
+    )
+    .await;
+    confirm(&mailer, &pool, "http://localhost:1337/<script>").await;
+
+    let sent = mailer.get_sent_emails();
+    assert_eq!(sent[0].subject, "[AnonPaste] Please confirm TICKET-1");
+    assert_eq!(
+        sent[0].html,
+        "<p>http:&#x2f;&#x2f;localhost:1337&#x2f;&lt;script&gt;</p>"
+    );
+    // Files missing from the directory fall back to the built-in templates.
+    assert!(sent[0].content.ends_with("Kind Regards,\nAnonPaste Team"));
+    std::fs::remove_dir_all(dir).unwrap();
+
+    let dir = temp_dir("templates");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("report_outcome.txt"), "{% if outcome %}").unwrap();
+    assert!(Templates::load(Some(&dir)).is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn admin_previews_templates() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    let app = router.with_state(app_state);
+    let get = |uri: &str| {
+        Request::builder()
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", &config.admin_token))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(get("/api/mail/templates"))
+        .await
+        .unwrap();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let templates: Value = serde_json::from_slice(&body).unwrap();
+    assert!(templates
+        .as_array()
+        .unwrap()
+        .contains(&"report_outcome".into()));
+
+    let response = app
+        .clone()
+        .oneshot(get("/api/mail/templates/report_outcome"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let preview: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(preview["subject"], "DMCA Report Resolved");
+    assert!(preview["text"]
+        .as_str()
+        .unwrap()
+        .contains("We reviewed the content and have taken it down."));
+    assert!(preview["html"].as_str().unwrap().contains("<li><a href="));
+
+    let response = app
+        .clone()
+        .oneshot(get("/api/mail/templates/report_outcome?locale=de-CH"))
+        .await
+        .unwrap();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let preview: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(preview["subject"], "DMCA-Meldung abgeschlossen");
+    assert!(preview["html"]
+        .as_str()
+        .unwrap()
+        .contains("<html lang=\"de\">"));
+
+    let response = app
+        .oneshot(get("/api/mail/templates/layout"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn template_previews_require_admin_token() {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    assert_admin_only(
+        &router.with_state(app_state),
+        &[
+            ("GET", "/api/mail/templates"),
+            ("GET", "/api/mail/templates/layout"),
+        ],
+    )
+    .await;
+}
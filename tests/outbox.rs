@@ -0,0 +1,219 @@
+use anonpaste::{
+    mailer::{
+        templates::Templates,
+        transport::{MailTransport, MemoryTransport},
+        Mailer, ReportMessage,
+    },
+    models::audit::{AuditEntry, ListAudit},
+    models::outbox::{ListOutbox, OutboxEntry, OutboxStatus, MAX_ATTEMPTS},
+    models::paste::{CreatePaste, Paste},
+    models::report::{CreateReport, Report, ReportCategory},
+    server::{get_app, get_test_config},
+    tasks::run_outbox_worker,
+};
+use async_trait::async_trait;
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::{assert_admin_only, sent_emails};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+mod common;
+
+/// Captures messages like the in-memory transport, or fails while `down`.
+#[derive(Default)]
+struct FlakyTransport {
+    down: AtomicBool,
+    inner: MemoryTransport,
+}
+
+#[async_trait]
+impl MailTransport for FlakyTransport {
+    async fn send(&self, message: &ReportMessage) -> anyhow::Result<()> {
+        if self.down.load(Ordering::SeqCst) {
+            anyhow::bail!("connection refused");
+        }
+        self.inner.send(message).await
+    }
+
+    fn captured(&self) -> Vec<ReportMessage> {
+        self.inner.captured()
+    }
+}
+
+#[tokio::test]
+async fn failed_emails_are_retried_then_dead_lettered() {
+    let config = get_test_config();
+    let (router, mut app_state) = get_app(&config).await.unwrap();
+    let transport = Arc::new(FlakyTransport::default());
+    app_state.mailer = Mailer::new(
+        transport.clone(),
+        Templates::load(None).unwrap(),
+        config.report_encryption_keys.clone(),
+        config.email_from.clone(),
+        config.email_name.clone(),
+    );
+    let app = router.with_state(app_state.clone());
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    // The report is saved even though its confirmation cannot be sent.
+    transport.down.store(true, Ordering::SeqCst);
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Please remove this".to_string(),
+            email: "reporter@example.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: Vec::new(),
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    assert!(sent_emails(&app_state).await.is_empty());
+
+    let keyring = &app_state.report_settings.keyring;
+    let outbox = OutboxEntry::list(&app_state.pool, keyring, ListOutbox { status: None })
+        .await
+        .unwrap();
+    assert_eq!(outbox.len(), 1);
+    assert_eq!(outbox[0].template, "confirm_report");
+    assert_eq!(outbox[0].recipient, "reporter@example.com");
+    assert_eq!(outbox[0].status, OutboxStatus::Pending);
+    assert_eq!(outbox[0].attempts, 1);
+    assert_eq!(outbox[0].last_error.as_deref(), Some("connection refused"));
+    assert!(outbox[0].next_attempt_at > outbox[0].created_at);
+    let recipient: String = sqlx::query_scalar("SELECT recipient FROM email_outbox")
+        .fetch_one(&app_state.pool)
+        .await
+        .unwrap();
+    assert!(!recipient.contains('@'));
+
+    // Nothing is due until the backoff has passed.
+    assert_eq!(
+        app_state.mailer.deliver_due(&app_state.pool).await.unwrap(),
+        0
+    );
+    for _ in 1..MAX_ATTEMPTS {
+        sqlx::query("UPDATE email_outbox SET next_attempt_at = 0")
+            .execute(&app_state.pool)
+            .await
+            .unwrap();
+        app_state.mailer.deliver_due(&app_state.pool).await.unwrap();
+    }
+    let dead = OutboxEntry::list(
+        &app_state.pool,
+        keyring,
+        ListOutbox {
+            status: Some(OutboxStatus::Dead),
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(dead.len(), 1);
+    assert_eq!(dead[0].attempts, MAX_ATTEMPTS);
+    sqlx::query("UPDATE email_outbox SET next_attempt_at = 0")
+        .execute(&app_state.pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        app_state.mailer.deliver_due(&app_state.pool).await.unwrap(),
+        0
+    );
+
+    transport.down.store(false, Ordering::SeqCst);
+    let retry = |id: i64| {
+        Request::builder()
+            .method("POST")
+            .uri(format!("/api/outbox/{}/retry", id))
+            .header("Authorization", format!("Bearer {}", &config.admin_token))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let response = app.clone().oneshot(retry(dead[0].id)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let sent = sent_emails(&app_state).await;
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].subject, "Confirm your DMCA Report");
+    assert!(
+        OutboxEntry::list(&app_state.pool, keyring, ListOutbox { status: None })
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    let response = app.oneshot(retry(dead[0].id)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let audit = AuditEntry::list(
+        &app_state.pool,
+        ListAudit {
+            cursor: None,
+            limit: None,
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(audit.items[0].action, "outbox.retry");
+}
+
+#[tokio::test]
+async fn outbox_worker_is_woken_by_new_emails() {
+    let (_router, app_state) = get_app(&get_test_config()).await.unwrap();
+    tokio::spawn(run_outbox_worker(
+        app_state.pool.clone(),
+        app_state.mailer.clone(),
+    ));
+    // Let the worker through its first round while the outbox is empty.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Please remove this".to_string(),
+            email: "reporter@example.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: Vec::new(),
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    // Long before the worker's next regular round.
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while app_state.mailer.get_sent_emails().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the outbox worker was not woken");
+}
+
+#[tokio::test]
+async fn outbox_routes_require_admin_token() {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    assert_admin_only(
+        &router.with_state(app_state),
+        &[("GET", "/api/outbox"), ("POST", "/api/outbox/1/retry")],
+    )
+    .await;
+}
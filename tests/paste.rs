@@ -1,17 +1,22 @@
 use anonpaste::{
-    models::paste::{CreatePaste, Paste},
+    models::audit::AuditContext,
+    models::paste::{CreatePaste, Paste, PasteStatus, UpdatePasteStatus},
+    models::{now_millis, DAY_MILLIS},
     server::{get_app, get_test_config},
 };
 use axum::{
     body::{to_bytes, Body},
     http::{Request, StatusCode},
 };
+use common::assert_admin_only;
 use serde_json::{
     json,
     Value::{self, Null},
 };
 use tower::ServiceExt;
 
+mod common;
+
 #[tokio::test]
 async fn fetch_paste() {
     let (router, app_state) = get_app(&get_test_config()).await.unwrap();
@@ -47,6 +52,55 @@ async fn fetch_paste() {
     );
 }
 
+#[tokio::test]
+async fn pastes_are_only_cached_briefly_by_browsers() {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    let now = now_millis();
+    for (id, expiry_time) in [
+        ("forever", None),
+        ("tomorrow", Some(now + DAY_MILLIS)),
+        ("soon", Some(now + 30 * 1000)),
+    ] {
+        Paste::create(
+            &app_state.pool,
+            CreatePaste {
+                id: id.to_string(),
+                content: "Hello".to_string(),
+                expiry_time,
+                expiry_views: None,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let app = router.with_state(app_state);
+    let cache_control = |id: &str| {
+        let request = Request::builder()
+            .uri(format!("/api/paste/{}", id))
+            .body(Body::empty())
+            .unwrap();
+        let app = app.clone();
+        async move {
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            response.headers()["cache-control"]
+                .to_str()
+                .unwrap()
+                .to_string()
+        }
+    };
+    assert_eq!(cache_control("forever").await, "private, max-age=60");
+    assert_eq!(cache_control("tomorrow").await, "private, max-age=60");
+    let soon = cache_control("soon").await;
+    let max_age: i64 = soon
+        .strip_prefix("private, max-age=")
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((28..=30).contains(&max_age), "{}", soon);
+}
+
 #[tokio::test]
 async fn fetch_paste_not_found() {
     let (router, app_state) = get_app(&get_test_config()).await.unwrap();
@@ -160,3 +214,256 @@ async fn create_paste() {
         }
     )
 }
+
+#[tokio::test]
+async fn list_pastes() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    for (id, expiry_views) in [("active", None), ("burnt", Some(0))] {
+        Paste::create(
+            &app_state.pool,
+            CreatePaste {
+                id: id.to_string(),
+                content: "Hello".to_string(),
+                expiry_time: None,
+                expiry_views,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let response = router
+        .with_state(app_state)
+        .oneshot(
+            Request::builder()
+                .uri("/api/paste?expired=false&minSize=5")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["nextCursor"], Null);
+    let items = body["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["id"], "active");
+    assert_eq!(items[0]["size"], 5);
+    assert_eq!(items[0]["expired"], false);
+    assert_eq!(items[0]["reported"], false);
+}
+
+#[tokio::test]
+async fn inspect_paste_keeps_views() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: Some(1),
+        },
+    )
+    .await
+    .unwrap();
+
+    let response = router
+        .with_state(app_state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/api/paste/test-id/inspect")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["content"], "Hello");
+    assert_eq!(body["expiryViews"], 1);
+
+    let paste = Paste::view(&app_state.pool, "test-id".to_string())
+        .await
+        .unwrap();
+    assert_eq!(paste.expiry_views, Some(1));
+}
+
+#[tokio::test]
+async fn pinned_paste_does_not_expire() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: Some(0),
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let app = router.with_state(app_state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/api/paste/test-id/pin")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/paste/test-id")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn taken_down_paste_is_unavailable() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let app = router.with_state(app_state.clone());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/api/paste/test-id/status")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"status": "taken_down", "reason": "DMCA takedown"}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/paste/test-id")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body,
+        json!({"msg": "UNAVAILABLE_FOR_LEGAL_REASONS", "reason": "DMCA takedown"})
+    );
+
+    let paste = Paste::inspect(&app_state.pool, "test-id".to_string())
+        .await
+        .unwrap();
+    assert_eq!(paste.content, "Hello");
+}
+
+#[tokio::test]
+async fn quarantined_paste_is_not_served() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: Some(3),
+        },
+    )
+    .await
+    .unwrap();
+    Paste::set_status(
+        &app_state.pool,
+        &app_state.webhooks,
+        "test-id".to_string(),
+        UpdatePasteStatus {
+            status: PasteStatus::Quarantined,
+            reason: None,
+            report_id: None,
+        },
+        &AuditContext {
+            actor: "admin".to_string(),
+            reason: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let response = router
+        .with_state(app_state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/api/paste/test-id")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let paste = Paste::inspect(&app_state.pool, "test-id".to_string())
+        .await
+        .unwrap();
+    assert_eq!(paste.expiry_views, Some(3));
+}
+
+#[tokio::test]
+async fn paste_admin_routes_require_admin_token() {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    assert_admin_only(
+        &router.with_state(app_state),
+        &[
+            ("PUT", "/api/paste/test-id"),
+            ("DELETE", "/api/paste/test-id"),
+            ("GET", "/api/paste"),
+            ("GET", "/api/paste/test-id/inspect"),
+            ("PUT", "/api/paste/test-id/pin"),
+            ("DELETE", "/api/paste/test-id/pin"),
+            ("PUT", "/api/paste/test-id/status"),
+        ],
+    )
+    .await;
+}
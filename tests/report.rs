@@ -1,18 +1,53 @@
 use anonpaste::{
+    crypto::Keyring,
     mailer::ReportMessage,
-    models::report::{CreateReport, Report},
-    server::{get_app, get_test_config},
+    models::attachment::CreateAttachment,
+    models::audit::AuditContext,
+    models::counter_notice::{CounterNotice, CreateCounterNotice},
+    models::flagger::{CreateTrustedFlagger, ListTrustedFlaggers, TrustedFlagger},
+    models::paste::{CreatePaste, Paste, PasteStatus, UpdatePasteStatus},
+    models::report::{
+        CreateReport, CreateReportNote, CreateReportReply, ListReports, LookupTicket, Report,
+        ReportCategory, ReportStatus, TransitionReport,
+    },
+    server::{get_app, get_test_config, AppState},
 };
 use axum::{
     body::{to_bytes, Body},
     http::{Request, StatusCode},
 };
+use common::{admin, assert_admin_only, sent_emails};
 use serde_json::{
     json,
     Value::{self, Null},
 };
 use tower::ServiceExt;
 
+mod common;
+
+/// Follows the confirmation link of every report submitted so far.
+async fn confirm_reports(app_state: &AppState) {
+    for message in sent_emails(app_state).await {
+        let Some(token) = message
+            .content
+            .split("/report/verify/")
+            .nth(1)
+            .and_then(|rest| rest.split_whitespace().next())
+        else {
+            continue;
+        };
+        Report::verify(
+            &app_state.pool,
+            &app_state.mailer,
+            &app_state.webhooks,
+            &app_state.report_settings,
+            token.to_string(),
+        )
+        .await
+        .unwrap();
+    }
+}
+
 #[tokio::test]
 async fn fetch_reports() {
     let config = get_test_config();
@@ -20,18 +55,23 @@ async fn fetch_reports() {
     Report::create(
         &app_state.pool,
         &app_state.mailer,
+        &app_state.report_settings,
         CreateReport {
             links: [
-                "https://website/test-id#magic-key".to_string(),
-                "https://website/test-id-2#magic-key-2".to_string(),
+                "http://localhost:1337/test-id#magic-key".to_string(),
+                "http://localhost:1337/test-id-2#magic-key-2".to_string(),
             ]
             .to_vec(),
             message: "Hey, this is my client's content, please remove it".to_string(),
             email: "federico@leaksdown.apiplant.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: Vec::new(),
+            locale: None,
         },
     )
     .await
     .unwrap();
+    confirm_reports(&app_state).await;
 
     let response = router
         .with_state(app_state)
@@ -50,9 +90,10 @@ async fn fetch_reports() {
     assert_eq!(response.status(), StatusCode::OK);
     let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
     let body: Value = serde_json::from_slice(&body).unwrap();
+    let created_at = body["items"][0]["createdAt"].as_i64().unwrap();
     assert_eq!(
         body,
-        json!([{"email": "federico@leaksdown.apiplant.com", "links": ["https://website/test-id#magic-key", "https://website/test-id-2#magic-key-2"], "message": "Hey, this is my client's content, please remove it"}])
+        json!({"items": [{"id": 1, "email": "federico@leaksdown.apiplant.com", "links": ["http://localhost:1337/test-id#magic-key", "http://localhost:1337/test-id-2#magic-key-2"], "message": "Hey, this is my client's content, please remove it", "category": "dmca", "priority": 3, "status": "open", "createdAt": created_at}], "nextCursor": null})
     );
 }
 
@@ -65,12 +106,15 @@ async fn create_report() {
 
     let paste_payload = CreateReport {
         links: [
-            "https://website/test-id#magic-key".to_string(),
-            "https://website/test-id-2#magic-key-2".to_string(),
+            "http://localhost:1337/test-id#magic-key".to_string(),
+            "http://localhost:1337/test-id-2#magic-key-2".to_string(),
         ]
         .to_vec(),
         message: "Hey, this is my client's content, please remove it".to_string(),
         email: "federico@leaksdown.apiplant.com".to_string(),
+        category: ReportCategory::Dmca,
+        attachments: Vec::new(),
+        locale: None,
     };
     let body = serde_json::to_string(&paste_payload).unwrap();
 
@@ -92,38 +136,1400 @@ async fn create_report() {
     let body: Value = serde_json::from_slice(&body).unwrap();
     assert_eq!(body, json!(Null));
 
-    let reports = Report::list(&app_state.pool).await.unwrap();
-    let report = reports.first().unwrap();
+    assert!(Report::list(
+        &app_state.pool,
+        &app_state.report_settings.keyring,
+        ListReports::default()
+    )
+    .await
+    .unwrap()
+    .items
+    .is_empty());
+    let sent = sent_emails(&app_state).await;
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "federico@leaksdown.apiplant.com");
+    assert_eq!(sent[0].subject, "Confirm your DMCA Report");
+    assert!(sent[0]
+        .content
+        .contains("http://localhost:1337/report/verify/"));
+
+    confirm_reports(&app_state).await;
+    let reports = Report::list(
+        &app_state.pool,
+        &app_state.report_settings.keyring,
+        ListReports::default(),
+    )
+    .await
+    .unwrap();
+    let report = reports.items.first().unwrap();
 
     assert_eq!(
         *report,
         Report {
+            id: 1,
             links: [
-                "https://website/test-id#magic-key".to_string(),
-                "https://website/test-id-2#magic-key-2".to_string(),
+                "http://localhost:1337/test-id#magic-key".to_string(),
+                "http://localhost:1337/test-id-2#magic-key-2".to_string(),
             ]
             .to_vec(),
             message: "Hey, this is my client's content, please remove it".to_string(),
             email: "federico@leaksdown.apiplant.com".to_string(),
+            category: ReportCategory::Dmca,
+            priority: 3,
+            status: ReportStatus::Open,
+            created_at: report.created_at,
         }
     );
 
-    let sent = app_state.mailer.get_sent_emails();
+    let sent = sent_emails(&app_state).await;
 
     assert_eq!(
-        sent,
+        sent[1..],
         vec![ReportMessage {
             email_from: "test@test.com".to_string(), 
             email_name: "test test".to_string(), 
             to: "federico@leaksdown.apiplant.com".to_string(), 
-            content: "Thanks for reaching out and initiating our DMCA Report procedure.\nYou reported the following links:\n\nhttps://website/test-id#magic-key\nhttps://website/test-id-2#magic-key-2\n\nAn operator will get back to you within 24hrs.\n\nKind Regards,\nAnonPaste Team".to_string(), 
-            subject: "DMCA Report Initiated".to_string() 
+            content: "Thanks for reaching out and initiating our DMCA Report procedure.\nYou reported the following links:\n\nhttp://localhost:1337/test-id#magic-key\nhttp://localhost:1337/test-id-2#magic-key-2\n\nAn operator will get back to you within 24hrs.\n\nKind Regards,\ntest test Team".to_string(), 
+            subject: "DMCA Report Initiated".to_string(),
+            ..sent[1].clone()
         }, ReportMessage {
             email_from: "test@test.com".to_string(), 
             email_name: "test test".to_string(), 
             to: "test@test.com".to_string(), 
-            content: "These links have been reported by federico@leaksdown.apiplant.com:\n\nhttps://website/test-id#magic-key\nhttps://website/test-id-2#magic-key-2\n\nAnonPaste Team".to_string(), 
-            subject: "DMCA Report Initiated".to_string() 
+            content: "These links have been reported by federico@leaksdown.apiplant.com:\n\nhttp://localhost:1337/test-id#magic-key\nhttp://localhost:1337/test-id-2#magic-key-2\n\ntest test Team".to_string(), 
+            subject: "DMCA Report Initiated".to_string(),
+            ..sent[2].clone()
         }]
     );
 }
+
+#[tokio::test]
+async fn reports_grouped_by_paste_and_auto_hidden() {
+    let mut config = get_test_config();
+    config.auto_hide_report_threshold = Some(2);
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    for email in ["first@reporter.com", "second@reporter.com"] {
+        Report::create(
+            &app_state.pool,
+            &app_state.mailer,
+            &app_state.report_settings,
+            CreateReport {
+                links: vec!["http://localhost:1337/paste/test-id#magic-key".to_string()],
+                message: "Please remove it".to_string(),
+                email: email.to_string(),
+                category: ReportCategory::Dmca,
+                attachments: Vec::new(),
+                locale: None,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let paste = Paste::inspect(&app_state.pool, "test-id".to_string())
+        .await
+        .unwrap();
+    assert_eq!(paste.status, PasteStatus::Active);
+    assert!(!paste.reported);
+
+    confirm_reports(&app_state).await;
+    let paste = Paste::inspect(&app_state.pool, "test-id".to_string())
+        .await
+        .unwrap();
+    assert_eq!(paste.status, PasteStatus::Quarantined);
+    assert!(paste.reported);
+
+    let response = router
+        .with_state(app_state)
+        .oneshot(
+            Request::builder()
+                .uri("/api/report/by-paste")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body,
+        json!([{"pasteId": "test-id", "reportCount": 2, "reportIds": [1, 2]}])
+    );
+}
+
+#[tokio::test]
+async fn report_case_workflow() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/paste/test-id#magic-key".to_string()],
+            message: "Please remove it".to_string(),
+            email: "federico@leaksdown.apiplant.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: Vec::new(),
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    confirm_reports(&app_state).await;
+
+    let app = router.with_state(app_state.clone());
+    let admin_request = |method: &str, uri: &str, body: Value| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", format!("Bearer {}", &config.admin_token))
+            .header("content-type", "application/json")
+            .header("x-actor", "alice")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(admin_request(
+            "POST",
+            "/api/report/1/transition",
+            json!({"status": "actioned"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    for (method, uri, body) in [
+        (
+            "PUT",
+            "/api/report/1/assignee",
+            json!({"assignee": "alice"}),
+        ),
+        (
+            "POST",
+            "/api/report/1/notes",
+            json!({"body": "Looks legit"}),
+        ),
+        (
+            "POST",
+            "/api/report/1/transition",
+            json!({"status": "in_review"}),
+        ),
+        (
+            "POST",
+            "/api/report/1/transition",
+            json!({"status": "actioned"}),
+        ),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(admin_request(method, uri, body))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/report/1")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["status"], "actioned");
+    assert_eq!(body["assignee"], "alice");
+    assert_eq!(body["notes"][0]["author"], "alice");
+    assert_eq!(body["notes"][0]["body"], "Looks legit");
+    assert!(body["resolvedAt"].is_i64());
+
+    let sent = sent_emails(&app_state).await;
+    let outcome = sent.last().unwrap();
+    assert_eq!(outcome.to, "federico@leaksdown.apiplant.com");
+    assert_eq!(outcome.subject, "DMCA Report Resolved");
+    assert!(outcome
+        .content
+        .contains("We reviewed the content and have taken it down."));
+}
+
+#[tokio::test]
+async fn create_report_validation() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+
+    let payload = CreateReport {
+        links: [
+            "http://localhost:1337/test-id#magic-key".to_string(),
+            "https://elsewhere.com/test-id#magic-key".to_string(),
+            "not a link".to_string(),
+        ]
+        .to_vec(),
+        message: "Hey, this is my client's content, please remove it".to_string(),
+        email: "federico at leaksdown".to_string(),
+        category: ReportCategory::Dmca,
+        attachments: Vec::new(),
+        locale: None,
+    };
+
+    let response = router
+        .with_state(app_state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/report")
+                .header("x-real-ip", "127.0.0.1")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_string(&payload).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body,
+        json!({"msg": "VALIDATION_FAILED", "errors": [
+            {"field": "email", "msg": "INVALID_EMAIL"},
+            {"field": "links[1]", "msg": "FOREIGN_LINK"},
+            {"field": "links[2]", "msg": "INVALID_LINK"},
+        ]})
+    );
+    assert!(Report::list(
+        &app_state.pool,
+        &app_state.report_settings.keyring,
+        ListReports::default()
+    )
+    .await
+    .unwrap()
+    .items
+    .is_empty());
+    assert!(sent_emails(&app_state).await.is_empty());
+}
+
+#[tokio::test]
+async fn create_report_deduplicates_links() {
+    let (_router, app_state) = get_app(&get_test_config()).await.unwrap();
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: [
+                "http://localhost:1337/test-id#magic-key".to_string(),
+                " http://localhost:1337/test-id#magic-key".to_string(),
+            ]
+            .to_vec(),
+            message: "Please remove it".to_string(),
+            email: "federico@leaksdown.apiplant.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: Vec::new(),
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    confirm_reports(&app_state).await;
+
+    let reports = Report::list(
+        &app_state.pool,
+        &app_state.report_settings.keyring,
+        ListReports::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        reports.items.first().unwrap().links,
+        vec!["http://localhost:1337/test-id#magic-key".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn list_reports_filters_and_paginates() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    for email in ["a@reporter.com", "b@reporter.com", "a@reporter.com"] {
+        Report::create(
+            &app_state.pool,
+            &app_state.mailer,
+            &app_state.report_settings,
+            CreateReport {
+                links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+                message: "Please remove it".to_string(),
+                email: email.to_string(),
+                category: ReportCategory::Dmca,
+                attachments: Vec::new(),
+                locale: None,
+            },
+        )
+        .await
+        .unwrap();
+    }
+    confirm_reports(&app_state).await;
+
+    let app = router.with_state(app_state);
+    let list = |uri: &str| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri(uri)
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+
+    let response = list("/api/report?limit=2").await.unwrap();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let ids: Vec<i64> = body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|report| report["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![3, 2]);
+    assert_eq!(body["nextCursor"], 2);
+
+    let response = list("/api/report?limit=2&cursor=2").await.unwrap();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["items"][0]["id"], 1);
+    assert_eq!(body["nextCursor"], Null);
+
+    let response = list("/api/report?email=A@reporter.com&sort=oldest&status=open")
+        .await
+        .unwrap();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let ids: Vec<i64> = body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|report| report["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![1, 3]);
+}
+
+#[tokio::test]
+async fn unconfirmed_reports_expire() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Please remove it".to_string(),
+            email: "federico@leaksdown.apiplant.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: Vec::new(),
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let response = router
+        .with_state(app_state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/report/verify/not-a-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    assert_eq!(
+        Report::expire_unverified(&app_state.pool, i64::MAX)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(sent_emails(&app_state).await.len(), 1);
+}
+
+#[tokio::test]
+async fn reporter_looks_up_ticket_status() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Please remove it".to_string(),
+            email: "federico@leaksdown.apiplant.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: Vec::new(),
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    let confirmation = sent_emails(&app_state).await.pop().unwrap();
+    let ticket_id = confirmation
+        .content
+        .split("Your ticket id is ")
+        .nth(1)
+        .and_then(|rest| rest.split('.').next())
+        .unwrap()
+        .to_string();
+    assert!(ticket_id.starts_with("AP-"));
+
+    let ticket = Report::lookup(
+        &app_state.pool,
+        &app_state.report_settings.keyring,
+        LookupTicket {
+            ticket_id: ticket_id.clone(),
+            email: "federico@leaksdown.apiplant.com".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+    assert!(!ticket.verified);
+
+    confirm_reports(&app_state).await;
+    let app = router.with_state(app_state);
+    let lookup = |email: &str| {
+        app.clone().oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/report/status")
+                .header("x-real-ip", "127.0.0.1")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"ticketId": ticket_id, "email": email}).to_string(),
+                ))
+                .unwrap(),
+        )
+    };
+
+    let response = lookup("Federico@LeaksDown.apiplant.com").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["ticketId"], ticket_id);
+    assert_eq!(body["status"], "open");
+    assert_eq!(body["verified"], true);
+    assert!(body.get("message").is_none());
+    assert!(body.get("notes").is_none());
+
+    let response = lookup("someone@else.com").await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn operator_replies_are_threaded() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Please remove it".to_string(),
+            email: "federico@leaksdown.apiplant.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: Vec::new(),
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    confirm_reports(&app_state).await;
+
+    let app = router.with_state(app_state.clone());
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/report/1/replies")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .header("content-type", "application/json")
+                .header("x-actor", "bob")
+                .body(Body::from(
+                    json!({"body": "Could you tell us which part of the paste is yours?"})
+                        .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let case = Report::get(&app_state.pool, &app_state.report_settings.keyring, 1)
+        .await
+        .unwrap();
+    let reply = sent_emails(&app_state).await.pop().unwrap();
+    assert_eq!(reply.to, "federico@leaksdown.apiplant.com");
+    assert_eq!(
+        reply.subject,
+        format!("Re: DMCA Report [{}]", case.ticket_id)
+    );
+    assert!(reply
+        .content
+        .starts_with("Could you tell us which part of the paste is yours?"));
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/report/1")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["messages"][0]["direction"], "outbound");
+    assert_eq!(body["messages"][0]["author"], "bob");
+    assert_eq!(body["messages"][0]["subject"], reply.subject);
+}
+
+#[tokio::test]
+async fn reporter_emails_are_localized() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    let app = router.with_state(app_state.clone());
+    // Each report comes from its own address, so the public rate limit
+    // does not get in the way.
+    let create = |body: Value, accept_language: &str, ip: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/report")
+            .header("x-real-ip", ip)
+            .header("content-type", "application/json")
+            .header("accept-language", accept_language)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let report = |email: &str| {
+        json!({
+            "links": ["http://localhost:1337/test-id#magic-key"],
+            "message": "Please remove it",
+            "email": email,
+            "category": "dmca",
+        })
+    };
+
+    // The header picks the closest supported language...
+    let response = app
+        .clone()
+        .oneshot(create(
+            report("de@example.com"),
+            "pt-BR, de-AT;q=0.9, en;q=0.8",
+            "127.0.0.1",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // ...unless the report names its own.
+    let mut french = report("fr@example.com");
+    french["locale"] = "fr".into();
+    let response = app
+        .clone()
+        .oneshot(create(french, "de", "127.0.0.2"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // Anything else falls back to English.
+    let mut unknown = report("xx@example.com");
+    unknown["locale"] = "tlh".into();
+    let response = app
+        .clone()
+        .oneshot(create(unknown, "de", "127.0.0.3"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let locales: Vec<String> = sqlx::query_scalar("SELECT locale FROM report ORDER BY id")
+        .fetch_all(&app_state.pool)
+        .await
+        .unwrap();
+    assert_eq!(locales, ["de", "fr", "en"]);
+
+    let sent = sent_emails(&app_state).await;
+    assert_eq!(sent[0].subject, "Bitte bestätigen Sie Ihre DMCA-Meldung");
+    assert!(sent[0]
+        .content
+        .ends_with("Mit freundlichen Grüßen\nIhr test test-Team"));
+    assert!(sent[0].html.contains("<html lang=\"de\">"));
+    assert_eq!(sent[1].subject, "Confirmez votre signalement DMCA");
+    assert_eq!(sent[2].subject, "Confirm your DMCA Report");
+
+    // Later emails are written in the report's language, while operators
+    // always hear about it in English.
+    confirm_reports(&app_state).await;
+    let sent = sent_emails(&app_state).await;
+    let received = sent
+        .iter()
+        .find(|message| message.to == "de@example.com" && message.subject != sent[0].subject)
+        .unwrap();
+    assert_eq!(received.subject, "DMCA-Meldung eingegangen");
+    let forwarded = sent
+        .iter()
+        .find(|message| message.to == config.email_from)
+        .unwrap();
+    assert_eq!(forwarded.subject, "DMCA Report Initiated");
+
+    let ctx = AuditContext {
+        actor: "admin".to_string(),
+        reason: None,
+    };
+    Report::transition(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings.keyring,
+        1,
+        TransitionReport {
+            status: ReportStatus::Rejected,
+        },
+        &ctx,
+    )
+    .await
+    .unwrap();
+    let outcome = sent_emails(&app_state).await.pop().unwrap();
+    assert_eq!(outcome.to, "de@example.com");
+    assert_eq!(outcome.subject, "DMCA-Meldung abgeschlossen");
+    assert!(outcome
+        .content
+        .contains("keinen Grund gefunden, dagegen vorzugehen."));
+
+    let reply = Report::reply(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings.keyring,
+        2,
+        CreateReportReply {
+            body: "Merci".to_string(),
+        },
+        &ctx,
+    )
+    .await
+    .unwrap();
+    assert!(reply.subject.starts_with("Re : signalement DMCA [AP-"));
+}
+
+fn attachment(content_type: &str, content: &str) -> CreateAttachment {
+    CreateAttachment {
+        filename: "../evidence/notice.pdf".to_string(),
+        content_type: content_type.to_string(),
+        content: content.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn report_attachments_are_admin_only_and_deleted_with_report() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Signed notice attached".to_string(),
+            email: "federico@leaksdown.apiplant.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: vec![attachment("application/pdf", "JVBERi0xLjQK")],
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    confirm_reports(&app_state).await;
+
+    let case = Report::get(&app_state.pool, &app_state.report_settings.keyring, 1)
+        .await
+        .unwrap();
+    assert_eq!(case.attachments.len(), 1);
+    assert_eq!(case.attachments[0].filename, "notice.pdf");
+    assert_eq!(case.attachments[0].size, 9);
+
+    let app = router.with_state(app_state.clone());
+    let download = || {
+        app.clone().oneshot(
+            Request::builder()
+                .uri(format!(
+                    "/api/report/1/attachments/{}",
+                    case.attachments[0].id
+                ))
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+    let response = download().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/pdf");
+    assert_eq!(
+        response.headers()["content-disposition"],
+        "attachment; filename=\"notice.pdf\""
+    );
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&body[..], b"%PDF-1.4\n");
+
+    let ctx = AuditContext::system("test");
+    Report::delete(&app_state.pool, "1".to_string(), &ctx)
+        .await
+        .unwrap();
+    assert_eq!(download().await.unwrap().status(), StatusCode::NOT_FOUND);
+    Report::purge_trash(&app_state.pool, i64::MAX)
+        .await
+        .unwrap();
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM report_attachment")
+        .fetch_one(&app_state.pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 0);
+}
+
+#[tokio::test]
+async fn report_attachments_are_validated() {
+    let (_router, app_state) = get_app(&get_test_config()).await.unwrap();
+    let create = |attachments: Vec<CreateAttachment>| {
+        Report::create(
+            &app_state.pool,
+            &app_state.mailer,
+            &app_state.report_settings,
+            CreateReport {
+                links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+                message: "Please remove it".to_string(),
+                email: "federico@leaksdown.apiplant.com".to_string(),
+                category: ReportCategory::Dmca,
+                attachments,
+                locale: None,
+            },
+        )
+    };
+
+    let error = create(vec![attachment("image/png", "aGk="); 4])
+        .await
+        .unwrap_err();
+    assert_eq!(error.to_string(), "VALIDATION_FAILED");
+
+    let too_large = "A".repeat(3 * 1024 * 1024);
+    match create(vec![
+        attachment("text/html", "aGk="),
+        attachment("image/png", "not base64!"),
+        attachment("image/png", &too_large),
+    ])
+    .await
+    {
+        Err(anonpaste::error::Error::Validation(errors)) => {
+            let codes: Vec<&str> = errors.iter().map(|e| e.msg.as_str()).collect();
+            assert_eq!(
+                codes,
+                vec![
+                    "UNSUPPORTED_ATTACHMENT_TYPE",
+                    "INVALID_ATTACHMENT",
+                    "ATTACHMENT_TOO_LARGE"
+                ]
+            );
+        }
+        _ => panic!("expected a validation error"),
+    }
+    assert!(sent_emails(&app_state).await.is_empty());
+}
+
+#[tokio::test]
+async fn reports_are_routed_and_sorted_by_category() {
+    let mut config = get_test_config();
+    config.report_recipients.insert(
+        ReportCategory::Csam,
+        vec!["safety@test.com".to_string(), "oncall@test.com".to_string()],
+    );
+    let (router, app_state) = get_app(&config).await.unwrap();
+    for category in [
+        ReportCategory::Dmca,
+        ReportCategory::Harassment,
+        ReportCategory::Csam,
+    ] {
+        Report::create(
+            &app_state.pool,
+            &app_state.mailer,
+            &app_state.report_settings,
+            CreateReport {
+                links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+                message: "Please remove it".to_string(),
+                email: "federico@leaksdown.apiplant.com".to_string(),
+                category,
+                attachments: Vec::new(),
+                locale: None,
+            },
+        )
+        .await
+        .unwrap();
+    }
+    confirm_reports(&app_state).await;
+
+    let sent = sent_emails(&app_state).await;
+    let csam: Vec<_> = sent
+        .iter()
+        .filter(|message| message.subject == "CSAM Report Initiated")
+        .collect();
+    let recipients: Vec<&str> = csam.iter().map(|message| message.to.as_str()).collect();
+    assert_eq!(
+        recipients,
+        vec![
+            "federico@leaksdown.apiplant.com",
+            "safety@test.com",
+            "oncall@test.com"
+        ]
+    );
+    assert!(csam[0].content.contains("within 1hr."));
+
+    let app = router.with_state(app_state);
+    let list = |uri: &str| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri(uri)
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+    let response = list("/api/report?sort=priority&limit=2").await.unwrap();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let categories: Vec<&str> = body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|report| report["category"].as_str().unwrap())
+        .collect();
+    assert_eq!(categories, vec!["csam", "harassment"]);
+
+    let uri = format!(
+        "/api/report?sort=priority&limit=2&cursor={}",
+        body["nextCursor"]
+    );
+    let response = list(&uri).await.unwrap();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["items"][0]["category"], "dmca");
+    assert_eq!(body["nextCursor"], Null);
+}
+
+#[tokio::test]
+async fn closed_reports_are_anonymised_after_retention() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Signed notice attached".to_string(),
+            email: "federico@leaksdown.apiplant.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: vec![attachment("application/pdf", "JVBERi0xLjQK")],
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    confirm_reports(&app_state).await;
+    let ctx = AuditContext::system("test");
+    Report::reply(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings.keyring,
+        1,
+        CreateReportReply {
+            body: "Thanks, we are on it.".to_string(),
+        },
+        &ctx,
+    )
+    .await
+    .unwrap();
+    Paste::set_status(
+        &app_state.pool,
+        &app_state.webhooks,
+        "test-id".to_string(),
+        UpdatePasteStatus {
+            status: PasteStatus::TakenDown,
+            reason: None,
+            report_id: Some(1),
+        },
+        &ctx,
+    )
+    .await
+    .unwrap();
+    CounterNotice::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        "test-id".to_string(),
+        CreateCounterNotice {
+            name: "Jane Uploader".to_string(),
+            email: "jane@uploader.com".to_string(),
+            address: "1 Main St, Springfield".to_string(),
+            statement: "The content was removed by mistake.".to_string(),
+            consent_to_jurisdiction: true,
+            signature: "Jane Uploader".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+    Report::transition(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings.keyring,
+        1,
+        TransitionReport {
+            status: ReportStatus::Closed,
+        },
+        &ctx,
+    )
+    .await
+    .unwrap();
+
+    // Closed just now, so nothing is due under the default 90 days.
+    let response = router
+        .with_state(app_state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/api/report/retention")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body, json!([]));
+
+    let due = Report::retention_due(&app_state.pool, i64::MAX)
+        .await
+        .unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].id, 1);
+    // A dry run changes nothing.
+    let case = Report::get(&app_state.pool, &app_state.report_settings.keyring, 1)
+        .await
+        .unwrap();
+    assert_eq!(case.email, "federico@leaksdown.apiplant.com");
+
+    assert_eq!(
+        Report::anonymise_closed(&app_state.pool, i64::MAX)
+            .await
+            .unwrap(),
+        1
+    );
+    let case = Report::get(&app_state.pool, &app_state.report_settings.keyring, 1)
+        .await
+        .unwrap();
+    assert_eq!(case.email, "");
+    assert_eq!(case.message, "");
+    assert_eq!(case.messages[0].body, "");
+    assert!(case.attachments.is_empty());
+    let notice = &case.counter_notices[0];
+    assert_eq!(
+        (
+            notice.name.as_str(),
+            notice.email.as_str(),
+            notice.address.as_str()
+        ),
+        ("", "", "")
+    );
+    assert_eq!(
+        (notice.statement.as_str(), notice.signature.as_str()),
+        ("", "")
+    );
+    assert_eq!(notice.paste_id, "test-id");
+    assert_eq!(case.status, ReportStatus::Closed);
+    assert_eq!(case.links, vec!["http://localhost:1337/test-id#magic-key"]);
+    assert!(Report::retention_due(&app_state.pool, i64::MAX)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn retention_counts_from_latest_closing() {
+    let (_, app_state) = get_app(&get_test_config()).await.unwrap();
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Signed notice attached".to_string(),
+            email: "federico@leaksdown.apiplant.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: vec![],
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    confirm_reports(&app_state).await;
+    let ctx = admin();
+    let transition = |status| {
+        Report::transition(
+            &app_state.pool,
+            &app_state.mailer,
+            &app_state.report_settings.keyring,
+            1,
+            TransitionReport { status },
+            &ctx,
+        )
+    };
+    let closed_at = || async {
+        sqlx::query_scalar::<_, Option<i64>>("SELECT closed_at FROM report WHERE id = 1")
+            .fetch_one(&app_state.pool)
+            .await
+            .unwrap()
+    };
+
+    // Rejected long ago but closed only now: not due yet.
+    transition(ReportStatus::Rejected).await.unwrap();
+    sqlx::query("UPDATE report SET resolved_at = 0 WHERE id = 1")
+        .execute(&app_state.pool)
+        .await
+        .unwrap();
+    transition(ReportStatus::Closed).await.unwrap();
+    assert!(Report::retention_due(&app_state.pool, 1)
+        .await
+        .unwrap()
+        .is_empty());
+
+    transition(ReportStatus::Open).await.unwrap();
+    assert_eq!(closed_at().await, None);
+    transition(ReportStatus::Closed).await.unwrap();
+    assert!(closed_at().await.is_some());
+    sqlx::query("UPDATE report SET closed_at = 0 WHERE id = 1")
+        .execute(&app_state.pool)
+        .await
+        .unwrap();
+    let due = Report::retention_due(&app_state.pool, 1).await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].closed_at, 0);
+}
+
+#[tokio::test]
+async fn report_pii_is_encrypted_and_rotated() {
+    let (_router, app_state) = get_app(&get_test_config()).await.unwrap();
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "I am the rights holder".to_string(),
+            email: "Federico@leaksdown.apiplant.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: vec![attachment("application/pdf", "JVBERi0xLjQK")],
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    confirm_reports(&app_state).await;
+    // Send what the confirmation queued, so only stored data is left to
+    // re-encrypt.
+    app_state.mailer.flush(&app_state.pool).await;
+
+    let (email, message): (String, String) =
+        sqlx::query_as("SELECT email, message FROM report WHERE id = 1")
+            .fetch_one(&app_state.pool)
+            .await
+            .unwrap();
+    assert!(!email.contains("leaksdown"));
+    assert!(!message.contains("rights holder"));
+    let content: Vec<u8> = sqlx::query_scalar("SELECT content FROM report_attachment")
+        .fetch_one(&app_state.pool)
+        .await
+        .unwrap();
+    assert!(!content.starts_with(b"%PDF"));
+
+    // Filtering by email still works, whatever the case.
+    let keyring = &app_state.report_settings.keyring;
+    let reports = Report::list(
+        &app_state.pool,
+        keyring,
+        ListReports {
+            email: Some("federico@LEAKSDOWN.apiplant.com".to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(reports.items.len(), 1);
+    assert_eq!(reports.items[0].email, "Federico@leaksdown.apiplant.com");
+    assert_eq!(reports.items[0].message, "I am the rights holder");
+
+    // Rows stored before encryption are picked up by the re-encryption too.
+    sqlx::query(
+        "INSERT INTO report ( links, message, email, created_at, updated_at, verified_at, ticket_id )
+            VALUES ( 'http://localhost:1337/old', 'Old report', 'old@reporter.com', 1, 1, 1, 'AP-OLD' )",
+    )
+    .execute(&app_state.pool)
+    .await
+    .unwrap();
+    // Until then they are found by their plaintext address.
+    let old = Report::list(
+        &app_state.pool,
+        keyring,
+        ListReports {
+            email: Some(" Old@Reporter.com".to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(old.items.len(), 1);
+    assert_eq!(old.items[0].message, "Old report");
+    let ticket = Report::lookup(
+        &app_state.pool,
+        keyring,
+        LookupTicket {
+            ticket_id: "ap-old".to_string(),
+            email: "OLD@reporter.com".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(ticket.ticket_id, "AP-OLD");
+
+    let rotated = Keyring::new(vec![
+        ("new".to_string(), [9; 32]),
+        ("test".to_string(), [7; 32]),
+    ])
+    .unwrap();
+    assert_eq!(
+        Report::reencrypt(&app_state.pool, &rotated).await.unwrap(),
+        3
+    );
+    assert_eq!(
+        Report::reencrypt(&app_state.pool, &rotated).await.unwrap(),
+        0
+    );
+
+    let retired = Keyring::new(vec![("new".to_string(), [9; 32])]).unwrap();
+    let case = Report::get(&app_state.pool, &retired, 1).await.unwrap();
+    assert_eq!(case.email, "Federico@leaksdown.apiplant.com");
+    assert!(Report::get(&app_state.pool, keyring, 1).await.is_err());
+    let old = Report::list(
+        &app_state.pool,
+        &retired,
+        ListReports {
+            email: Some("old@reporter.com".to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(old.items[0].message, "Old report");
+}
+
+#[tokio::test]
+async fn case_pii_is_encrypted_and_rotated() {
+    let (_router, app_state) = get_app(&get_test_config()).await.unwrap();
+    let keyring = &app_state.report_settings.keyring;
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Please remove it".to_string(),
+            email: "reporter@leaksdown.com".to_string(),
+            category: ReportCategory::Dmca,
+            attachments: Vec::new(),
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    confirm_reports(&app_state).await;
+    Report::add_note(
+        &app_state.pool,
+        keyring,
+        1,
+        CreateReportNote {
+            body: "Called Jane on +1 555 0100".to_string(),
+        },
+        &admin(),
+    )
+    .await
+    .unwrap();
+    Paste::set_status(
+        &app_state.pool,
+        &app_state.webhooks,
+        "test-id".to_string(),
+        UpdatePasteStatus {
+            status: PasteStatus::TakenDown,
+            reason: None,
+            report_id: Some(1),
+        },
+        &admin(),
+    )
+    .await
+    .unwrap();
+    CounterNotice::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        "test-id".to_string(),
+        CreateCounterNotice {
+            name: "Jane Uploader".to_string(),
+            email: "jane@uploader.com".to_string(),
+            address: "1 Main St, Springfield".to_string(),
+            statement: "The content was removed by mistake.".to_string(),
+            consent_to_jurisdiction: true,
+            signature: "Jane Uploader".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+    TrustedFlagger::create(
+        &app_state.pool,
+        keyring,
+        CreateTrustedFlagger {
+            name: "Example CERT".to_string(),
+            email: "abuse@cert.example".to_string(),
+            immediate_quarantine: None,
+        },
+        &admin(),
+    )
+    .await
+    .unwrap();
+
+    let stored: Vec<String> = sqlx::query_scalar(
+        "SELECT name || email || address || statement || signature FROM counter_notice
+            UNION ALL SELECT email FROM trusted_flagger
+            UNION ALL SELECT body FROM report_note",
+    )
+    .fetch_all(&app_state.pool)
+    .await
+    .unwrap();
+    assert_eq!(stored.len(), 3);
+    for value in &stored {
+        for plaintext in [
+            "Jane",
+            "uploader.com",
+            "Main St",
+            "mistake",
+            "cert.example",
+            "555",
+        ] {
+            assert!(!value.contains(plaintext), "{} in {}", plaintext, value);
+        }
+    }
+
+    let rotated = Keyring::new(vec![
+        ("new".to_string(), [9; 32]),
+        ("test".to_string(), [7; 32]),
+    ])
+    .unwrap();
+    assert!(Report::reencrypt(&app_state.pool, &rotated).await.unwrap() >= 4);
+    assert_eq!(
+        Report::reencrypt(&app_state.pool, &rotated).await.unwrap(),
+        0
+    );
+
+    let retired = Keyring::new(vec![("new".to_string(), [9; 32])]).unwrap();
+    let case = Report::get(&app_state.pool, &retired, 1).await.unwrap();
+    assert_eq!(case.notes[0].body, "Called Jane on +1 555 0100");
+    assert_eq!(case.counter_notices[0].name, "Jane Uploader");
+    assert_eq!(case.counter_notices[0].address, "1 Main St, Springfield");
+    let flaggers = TrustedFlagger::list(
+        &app_state.pool,
+        &retired,
+        ListTrustedFlaggers {
+            include_revoked: None,
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(flaggers[0].email, "abuse@cert.example");
+}
+
+#[tokio::test]
+async fn report_admin_routes_require_admin_token() {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    assert_admin_only(
+        &router.with_state(app_state),
+        &[
+            ("GET", "/api/report"),
+            ("GET", "/api/report/1"),
+            ("DELETE", "/api/report/1"),
+            ("POST", "/api/report/1/transition"),
+            ("PUT", "/api/report/1/assignee"),
+            ("POST", "/api/report/1/notes"),
+            ("GET", "/api/report/1/attachments/1"),
+            ("POST", "/api/report/1/replies"),
+            ("GET", "/api/report/by-paste?pasteId=test-id"),
+            ("GET", "/api/report/retention"),
+        ],
+    )
+    .await;
+}
@@ -0,0 +1,135 @@
+use anonpaste::{
+    models::paste::{CreatePaste, Paste},
+    server::{get_app, get_test_config},
+};
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+};
+use common::{admin, assert_admin_only};
+use serde_json::Value;
+use tower::ServiceExt;
+
+mod common;
+
+#[tokio::test]
+async fn restore_deleted_paste() {
+    let config = get_test_config();
+    let (router, app_state) = get_app(&config).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+    Paste::delete(
+        &app_state.pool,
+        &app_state.webhooks,
+        "test-id".to_string(),
+        &admin(),
+    )
+    .await
+    .unwrap();
+
+    let app = router.with_state(app_state.clone());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/paste/test-id")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/trash")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["pastes"][0]["id"], "test-id");
+    assert_eq!(body["reports"], Value::Array(vec![]));
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/trash/paste/test-id/restore")
+                .header("Authorization", format!("Bearer {}", &config.admin_token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let paste = Paste::view(&app_state.pool, "test-id".to_string())
+        .await
+        .unwrap();
+    assert_eq!(paste.content, "Hello");
+}
+
+#[tokio::test]
+async fn purge_trash() {
+    let (_router, app_state) = get_app(&get_test_config()).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+    Paste::delete(
+        &app_state.pool,
+        &app_state.webhooks,
+        "test-id".to_string(),
+        &admin(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(Paste::purge_trash(&app_state.pool, 0).await.unwrap(), 0);
+    assert_eq!(
+        Paste::purge_trash(&app_state.pool, i64::MAX).await.unwrap(),
+        1
+    );
+    assert!(Paste::trashed(&app_state.pool).await.unwrap().is_empty());
+    assert!(Paste::inspect(&app_state.pool, "test-id".to_string())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn trash_routes_require_admin_token() {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    assert_admin_only(
+        &router.with_state(app_state),
+        &[
+            ("GET", "/api/trash"),
+            ("POST", "/api/trash/paste/test-id/restore"),
+            ("POST", "/api/trash/report/1/restore"),
+        ],
+    )
+    .await;
+}
@@ -0,0 +1,223 @@
+use anonpaste::{
+    models::outbox::OutboxStatus,
+    models::paste::{CreatePaste, Paste, PasteStatus, UpdatePasteStatus},
+    models::report::{CreateReport, Report, ReportCategory},
+    models::webhook::{ListWebhookDeliveries, WebhookDelivery},
+    server::{get_app, get_test_config},
+    webhooks::{sign, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER},
+};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use common::{admin, assert_admin_only, sent_emails};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+mod common;
+
+/// Records every delivery it accepts, or answers 500 while `down`.
+#[derive(Clone, Default)]
+struct Receiver {
+    down: Arc<AtomicBool>,
+    received: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+}
+
+async fn receive(State(receiver): State<Receiver>, headers: HeaderMap, body: String) -> StatusCode {
+    if receiver.down.load(Ordering::SeqCst) {
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+    receiver.received.lock().unwrap().push((headers, body));
+    StatusCode::NO_CONTENT
+}
+
+async fn start_receiver() -> (Receiver, String) {
+    let receiver = Receiver::default();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+    let app = Router::new()
+        .route("/hooks", post(receive))
+        .with_state(receiver.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (receiver, url)
+}
+
+#[tokio::test]
+async fn moderation_events_are_signed_and_retried() {
+    let (receiver, url) = start_receiver().await;
+    let mut config = get_test_config();
+    config.webhook_urls = vec![url.clone()];
+    let (_router, app_state) = get_app(&config).await.unwrap();
+    Paste::create(
+        &app_state.pool,
+        CreatePaste {
+            id: "test-id".to_string(),
+            content: "Hello".to_string(),
+            expiry_time: None,
+            expiry_views: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    // Operators only hear about a report once its reporter confirms it.
+    Report::create(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.report_settings,
+        CreateReport {
+            links: vec!["http://localhost:1337/test-id#magic-key".to_string()],
+            message: "Please remove this".to_string(),
+            email: "reporter@example.com".to_string(),
+            category: ReportCategory::Malware,
+            attachments: Vec::new(),
+            locale: None,
+        },
+    )
+    .await
+    .unwrap();
+    assert!(receiver.received.lock().unwrap().is_empty());
+    let confirmation = sent_emails(&app_state).await.pop().unwrap();
+    let token = confirmation
+        .content
+        .split("/report/verify/")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap();
+    Report::verify(
+        &app_state.pool,
+        &app_state.mailer,
+        &app_state.webhooks,
+        &app_state.report_settings,
+        token.to_string(),
+    )
+    .await
+    .unwrap();
+    // No worker runs here to be woken, so deliver by hand.
+    app_state.webhooks.flush(&app_state.pool).await;
+
+    let (headers, body) = receiver.received.lock().unwrap()[0].clone();
+    let header = |name: &str| headers[name].to_str().unwrap().to_string();
+    let timestamp: i64 = header(TIMESTAMP_HEADER).parse().unwrap();
+    assert_eq!(
+        header(SIGNATURE_HEADER),
+        sign(&config.webhook_secret, timestamp, &body)
+    );
+    assert_ne!(
+        header(SIGNATURE_HEADER),
+        sign("wrong-secret", timestamp, &body)
+    );
+    assert_eq!(header(EVENT_HEADER), "report.created");
+    assert_eq!(header("content-type"), "application/json");
+    let event: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(event["id"], header(DELIVERY_HEADER));
+    assert_eq!(event["type"], "report.created");
+    assert_eq!(event["data"]["id"], 1);
+    assert_eq!(event["data"]["category"], "malware");
+    assert_eq!(event["data"]["priority"], 1);
+    assert_eq!(event["data"]["pasteIds"], serde_json::json!(["test-id"]));
+    assert!(event["data"]["ticketId"]
+        .as_str()
+        .unwrap()
+        .starts_with("AP-"));
+    // Neither paste keys nor anything the reporter wrote leave the server.
+    assert!(!body.contains("magic-key"));
+    assert!(!body.contains("reporter@example.com"));
+
+    // A failing endpoint does not fail the takedown; the event waits for a
+    // retry instead.
+    receiver.down.store(true, Ordering::SeqCst);
+    Paste::set_status(
+        &app_state.pool,
+        &app_state.webhooks,
+        "test-id".to_string(),
+        UpdatePasteStatus {
+            status: PasteStatus::TakenDown,
+            reason: Some("Malware".to_string()),
+            report_id: Some(1),
+        },
+        &admin(),
+    )
+    .await
+    .unwrap();
+    app_state.webhooks.flush(&app_state.pool).await;
+    let deliveries = WebhookDelivery::list(&app_state.pool, ListWebhookDeliveries { status: None })
+        .await
+        .unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].event, "paste.taken_down");
+    assert_eq!(deliveries[0].url, url);
+    assert_eq!(deliveries[0].status, OutboxStatus::Pending);
+    assert_eq!(deliveries[0].attempts, 1);
+    assert_eq!(
+        deliveries[0].last_error.as_deref(),
+        Some("endpoint answered 500 Internal Server Error")
+    );
+    assert_eq!(
+        app_state
+            .webhooks
+            .deliver_due(&app_state.pool)
+            .await
+            .unwrap(),
+        0
+    );
+
+    receiver.down.store(false, Ordering::SeqCst);
+    sqlx::query("UPDATE webhook_delivery SET next_attempt_at = 0")
+        .execute(&app_state.pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        app_state
+            .webhooks
+            .deliver_due(&app_state.pool)
+            .await
+            .unwrap(),
+        1
+    );
+    let (headers, body) = receiver.received.lock().unwrap()[1].clone();
+    let event: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(event["id"], deliveries[0].event_id);
+    assert_eq!(headers[DELIVERY_HEADER], deliveries[0].event_id.as_str());
+    assert_eq!(event["type"], "paste.taken_down");
+    assert_eq!(event["data"]["id"], "test-id");
+    assert_eq!(event["data"]["reason"], "Malware");
+    assert_eq!(event["data"]["reportId"], 1);
+
+    Paste::delete(
+        &app_state.pool,
+        &app_state.webhooks,
+        "test-id".to_string(),
+        &admin(),
+    )
+    .await
+    .unwrap();
+    app_state.webhooks.flush(&app_state.pool).await;
+    let (_, body) = receiver.received.lock().unwrap()[2].clone();
+    let event: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(event["type"], "paste.deleted");
+    assert_eq!(event["data"]["id"], "test-id");
+    assert!(
+        WebhookDelivery::list(&app_state.pool, ListWebhookDeliveries { status: None })
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn webhook_delivery_routes_require_admin_token() {
+    let (router, app_state) = get_app(&get_test_config()).await.unwrap();
+    assert_admin_only(
+        &router.with_state(app_state),
+        &[
+            ("GET", "/api/webhooks/deliveries"),
+            ("POST", "/api/webhooks/deliveries/1/retry"),
+        ],
+    )
+    .await;
+}